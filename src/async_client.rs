@@ -0,0 +1,471 @@
+extern crate std;
+extern crate tokio;
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use self::tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use self::tokio::net::TcpStream;
+use self::tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use self::tokio::sync::{mpsc, oneshot};
+use self::tokio::task::AbortHandle;
+
+use super::def::*;
+use super::def::OpcodeRequest::*;
+use super::def::CqlRequestBody::*;
+use super::def::RCErrorType::*;
+use super::def::CqlResponseBody::*;
+use super::frame::FrameBuffer;
+use super::client::{StreamIds, ConnectOptions, encode_request};
+use super::handshake::{Transport, handshake};
+use super::timestamp::{TimestampGenerator, MonotonicTimestampGenerator};
+
+// Requests waiting for a response, by stream, with the result metadata of the executions that skipped it.
+// Events pushed by the server go to the registered channel. Once the connection fails no more requests are accepted
+struct Dispatcher {
+    streams: StreamIds,
+    pending: BTreeMap<i16, oneshot::Sender<RCResult<CqlResponse>>>,
+    result_meta: BTreeMap<i16, CqlMetadata>,
+    events: Option<mpsc::UnboundedSender<CqlEvent>>,
+    closed: Option<CowStr>
+}
+
+impl Dispatcher {
+    fn new(max_abandoned: usize) -> Dispatcher {
+        Dispatcher { streams: StreamIds::new(max_abandoned), pending: BTreeMap::new(), result_meta: BTreeMap::new(), events: None, closed: None }
+    }
+
+    fn dispatch(&mut self, response: CqlResponse) {
+        if response.stream < 0 {
+            self.dispatch_event(response);
+        } else if let Some(sender) = self.pending.remove(&response.stream) {
+            self.streams.release(response.stream);
+            let _ = sender.send(Ok(response));
+        }
+        // Responses to abandoned requests are dropped
+    }
+
+    // The response is still awaited so that the stream can be reused, until too many are
+    fn abandon(&mut self, stream: i16, version: u8) {
+        if let Err(err) = self.streams.abandon(stream, version) {
+            self.close(err);
+        }
+    }
+
+    fn dispatch_event(&mut self, response: CqlResponse) {
+        if let ResponseEvent(event) = response.body {
+            let delivered = match self.events {
+                Some(ref sender) => sender.send(event).is_ok(),
+                None => false
+            };
+            if !delivered {
+                self.events = None;
+            }
+        }
+    }
+
+    fn close(&mut self, err: RCError) {
+        for (_, sender) in std::mem::take(&mut self.pending) {
+            let _ = sender.send(Err(RCError::new(err.desc.clone(), ConnectionError)));
+        }
+        self.closed = Some(err.desc);
+    }
+}
+
+///
+/// Reads whole frames from the connection and decodes them with a `FrameBuffer`. From protocol v5 on,
+/// the frames are first extracted from segments.
+///
+struct FrameReader<R> {
+    inner: R,
+    version: u8,
+    compression: Compression,
+    frames: FrameBuffer
+}
+
+impl<R: AsyncRead + Unpin> FrameReader<R> {
+    fn new(inner: R, version: u8) -> FrameReader<R> {
+        FrameReader { inner: inner, version: version, compression: Compression::None, frames: FrameBuffer::new() }
+    }
+
+    // Rows of executions that skipped their result metadata are decoded with the one in `result_meta`
+    fn decode_frame(&mut self, result_meta: &mut BTreeMap<i16, CqlMetadata>) -> RCResult<Option<CqlResponse>> {
+        Ok(try_rc!(self.frames.next_frame(self.version, result_meta), "Error reading response"))
+    }
+
+    async fn fill(&mut self) -> RCResult<()> {
+        let mut chunk = [0u8; 4096];
+        let n = try_io!(self.inner.read(&mut chunk).await, "Error reading response");
+        if n == 0 {
+            return Err(RCError::new("Connection closed by the server", ConnectionError))
+        }
+        try_rc!(self.frames.push(&chunk[..n]), "Error reading response");
+        Ok(())
+    }
+}
+
+///
+/// Asynchronous client. Requests can be issued concurrently from several tasks, they are multiplexed
+/// on a single connection. Background tasks write the requests and match the responses to them by stream.
+///
+pub struct AsyncClient {
+    requests: mpsc::UnboundedSender<Vec<u8>>,
+    dispatcher: Arc<Mutex<Dispatcher>>,
+    segments: bool,
+    pub version: u8,
+    pub compression: Compression,
+    pub supported: CqlStringMultiMap,
+    /// Ask the server not to send the result metadata of prepared statements, the one returned by PREPARE is used instead.
+    /// Off by default: before protocol v5 the server cannot tell that a schema change made that metadata stale, and
+    /// rows are then decoded with the wrong columns. From v5 on, pass Rows results to `CqlPreparedStat::update_result_metadata`
+    pub skip_metadata: bool,
+    /// Maximum time to wait for the response to a request, or forever if None
+    pub request_timeout: Option<Duration>,
+    /// Serial consistency of the conditional updates (lightweight transactions) sent by this client
+    pub serial_consistency: Option<Consistency>,
+    timestamp_generator: Option<Box<dyn TimestampGenerator + Send + Sync>>
+}
+
+impl AsyncClient {
+
+    // Default timestamps were introduced in protocol v3
+    fn default_timestamp(&self) -> Option<i64> {
+        match self.timestamp_generator {
+            Some(ref generator) if self.version >= 3 => Some(generator.next_timestamp()),
+            _ => None
+        }
+    }
+
+    ///
+    /// Replaces the generator of the timestamps sent with QUERY, EXECUTE and BATCH requests. With no generator
+    /// the server assigns the timestamps.
+    ///
+    pub fn set_timestamp_generator(&mut self, generator: Option<Box<dyn TimestampGenerator + Send + Sync>>) {
+        self.timestamp_generator = generator;
+    }
+
+    // The request is queued for the writer task, so a caller that gives up on it cannot leave a partially
+    // written frame behind. A request that times out is abandoned, its response is dropped when it arrives.
+    // Too many abandoned requests close the connection
+    async fn send_request<'a>(&self, request: &mut CqlRequest<'a>, result_meta: Option<CqlMetadata>,
+                              timeout: Option<Duration>) -> RCResult<CqlResponse> {
+        let receiver = {
+            let mut dispatcher = self.dispatcher.lock().unwrap();
+            if let Some(ref desc) = dispatcher.closed {
+                return Err(RCError::new(format!("Connection is closed: {}", desc), ConnectionError))
+            }
+            request.stream = try_rc!(dispatcher.streams.alloc(self.version), "Error allocating stream");
+            let data = match encode_request(request, self.version, self.compression, self.segments) {
+                Ok(data) => data,
+                Err(err) => {
+                    dispatcher.streams.release(request.stream);
+                    return Err(err)
+                }
+            };
+            if self.requests.send(data).is_err() {
+                dispatcher.streams.release(request.stream);
+                return Err(RCError::new("Connection is closed: writer stopped", ConnectionError))
+            }
+            let (sender, receiver) = oneshot::channel();
+            dispatcher.pending.insert(request.stream, sender);
+            match result_meta {
+                Some(meta) => { dispatcher.result_meta.insert(request.stream, meta); },
+                None => { dispatcher.result_meta.remove(&request.stream); }
+            }
+            receiver
+        };
+
+        let received = async {
+            match receiver.await {
+                Ok(response) => response,
+                Err(_) => Err(RCError::new("Connection closed before the response was received", ConnectionError))
+            }
+        };
+        let stream = request.stream;
+        let response = within(timeout.or(self.request_timeout), received, "Timed out waiting for response").await;
+        if let Err(ref err) = response {
+            if err.kind == TimeoutError {
+                self.dispatcher.lock().unwrap().abandon(stream, self.version);
+            }
+        }
+        response
+    }
+
+    // Idempotent statements are sent once more when their response times out. Their timestamp is set
+    // beforehand, so the second attempt applies as the first one would have
+    async fn send_statement(&self, request: &mut CqlRequest<'_>, result_meta: Option<CqlMetadata>, options: &QueryOptions) -> RCResult<CqlResponse> {
+        match self.send_request(request, result_meta.clone(), options.timeout).await {
+            Err(ref err) if err.kind == TimeoutError && options.idempotent => self.send_request(request, result_meta, options.timeout).await,
+            response => response
+        }
+    }
+
+    pub async fn exec_query<O: Into<QueryOptions>>(&self, query_str: &str, values: &[CqlValue], options: O) -> RCResult<CqlResponse> {
+        let options = options.into();
+        let mut params = options.query_params(values);
+        if params.serial_consistency.is_none() {
+            params.serial_consistency = self.serial_consistency;
+        }
+        if params.timestamp.is_none() {
+            params.timestamp = self.default_timestamp();
+        }
+        let mut q = CqlRequest {
+            flags: if options.tracing { 0x02 } else { 0x00 },
+            stream: 0x00,
+            opcode: OpcodeQuery,
+            body: RequestQuery(query_str, params)};
+
+        Ok(try_rc!(self.send_statement(&mut q, None, &options).await, "Error executing query"))
+    }
+
+    pub async fn prepared_statement(&self, query_str: &str) -> RCResult<CqlPreparedStat> {
+        let mut q = CqlRequest {
+            flags: 0x00,
+            stream: 0x00,
+            opcode: OpcodePrepare,
+            body: RequestPrepare(query_str),
+        };
+
+        let res = try_rc!(self.send_request(&mut q, None, None).await, "Error preparing statement");
+        match res.body {
+            ResultPrepared(preps) => Ok(preps),
+            ResponseError(_, ref msg) => Err(RCError::new(format!("Error preparing statement: {}", msg), ReadError)),
+            _ => Err(RCError::new("Response does not contain prepared statement", ReadError))
+        }
+    }
+
+    pub async fn exec_prepared<O: Into<QueryOptions>>(&self, preps: &CqlPreparedStat, params: &[CqlValue], options: O) -> RCResult<CqlResponse> {
+        let options = options.into();
+        let result_meta = match preps.meta_result {
+            Some(ref meta) if self.skip_metadata && meta.column_count > 0 => Some(meta.clone()),
+            _ => None
+        };
+        let mut params = options.query_params(params);
+        params.skip_metadata = result_meta.is_some();
+        // The keyspace of a prepared statement is the one it was prepared in
+        params.keyspace = None;
+        if params.serial_consistency.is_none() {
+            params.serial_consistency = self.serial_consistency;
+        }
+        if params.timestamp.is_none() {
+            params.timestamp = self.default_timestamp();
+        }
+        let mut q = CqlRequest {
+            flags: if options.tracing { 0x02 } else { 0x00 },
+            stream: 0x00,
+            opcode: OpcodeExecute,
+            body: RequestExec(preps, params),
+        };
+
+        Ok(try_rc!(self.send_statement(&mut q, result_meta, &options).await, "Error executing prepared statement"))
+    }
+
+    pub async fn exec_batch<O: Into<QueryOptions>>(&self, q_type: BatchType, q_vec: Vec<Query>, options: O) -> RCResult<CqlResponse> {
+        let options = options.into();
+        let mut params = options.batch_params();
+        if params.serial_consistency.is_none() {
+            params.serial_consistency = self.serial_consistency;
+        }
+        if params.timestamp.is_none() {
+            params.timestamp = self.default_timestamp();
+        }
+        let mut q = CqlRequest {
+            flags: if options.tracing { 0x02 } else { 0x00 },
+            stream: 0x00,
+            opcode: OpcodeBatch,
+            body: RequestBatch(q_vec, q_type, params)};
+
+        Ok(try_rc!(self.send_statement(&mut q, None, &options).await, "Error executing batch"))
+    }
+
+    ///
+    /// Subscribes to server pushed events, which are delivered through the returned channel as they arrive.
+    ///
+    pub async fn register(&self, events: &[CqlEventType]) -> RCResult<mpsc::UnboundedReceiver<CqlEvent>> {
+        let mut q = CqlRequest {
+            flags: 0x00,
+            stream: 0x00,
+            opcode: OpcodeRegister,
+            body: RequestRegister(events),
+        };
+
+        // Events can follow READY before this task gets to run again, so the channel is set up beforehand
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.dispatcher.lock().unwrap().events = Some(sender);
+        let registered = match self.send_request(&mut q, None, None).await {
+            Ok(res) => match res.body {
+                ResponseReady => Ok(receiver),
+                ResponseError(_, ref msg) => Err(RCError::new(format!("Error registering for events: {}", msg), ReadError)),
+                _ => Err(RCError::new("Wrong response to register", ReadError))
+            },
+            Err(err) => Err(RCError::new(format!("Error registering for events -> {}", err.desc), err.kind))
+        };
+        if registered.is_err() {
+            self.dispatcher.lock().unwrap().events = None;
+        }
+        registered
+    }
+}
+
+// Fails with a `TimeoutError` if `future` does not complete within `timeout`
+async fn within<T, F: Future<Output = RCResult<T>>>(timeout: Option<Duration>, future: F, msg: &'static str) -> RCResult<T> {
+    match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, future).await {
+            Ok(result) => result,
+            Err(_) => Err(RCError::new(msg, TimeoutError))
+        },
+        None => future.await
+    }
+}
+
+// Connection for the handshake, a new one is opened for each protocol version tried. Until the
+// connection is ready requests are sent one at a time, so stream 0 is always used
+struct AsyncTransport<'a> {
+    ip: &'a str,
+    port: u16,
+    options: &'a ConnectOptions,
+    connection: Option<(FrameReader<OwnedReadHalf>, OwnedWriteHalf)>
+}
+
+impl<'a> Transport for AsyncTransport<'a> {
+    async fn reconnect(&mut self, version: u8) -> RCResult<()> {
+        let (ip, port) = (self.ip, self.port);
+        let connected = async {
+            match TcpStream::connect((ip, port)).await {
+                Ok(socket) => Ok(socket),
+                Err(_) => Err(RCError::new(format!("Failed to connect to server at {}:{}", ip, port), ConnectionError))
+            }
+        };
+        let socket = try_rc!(within(self.options.connect_timeout, connected, "Timed out connecting").await, "Error connecting");
+        let (read_half, writer) = socket.into_split();
+        self.connection = Some((FrameReader::new(read_half, version), writer));
+        Ok(())
+    }
+
+    async fn roundtrip(&mut self, request: &mut CqlRequest<'_>) -> RCResult<CqlResponse> {
+        let (reader, writer) = match self.connection {
+            Some((ref mut reader, ref mut writer)) => (reader, writer),
+            None => return Err(RCError::new("Not connected", ConnectionError))
+        };
+        let data = try_rc!(encode_request(request, reader.version, reader.compression, reader.frames.has_segments()), "Error encoding request");
+        let exchanged = async {
+            try_io!(writer.write_all(&data).await, "Error writing request");
+            loop {
+                if let Some(response) = try_rc!(reader.decode_frame(&mut BTreeMap::new()), "Error reading response") {
+                    return Ok(response)
+                }
+                try_rc!(reader.fill().await, "Error reading response");
+            }
+        };
+        within(self.options.request_timeout, exchanged, "Timed out waiting for response").await
+    }
+
+    fn expect_compression(&mut self, compression: Compression) {
+        if let Some((ref mut reader, _)) = self.connection {
+            reader.frames.set_compression(compression);
+        }
+    }
+
+    fn start_framing(&mut self, compression: Compression) {
+        if let Some((ref mut reader, _)) = self.connection {
+            reader.compression = compression;
+            if reader.version >= 5 {
+                reader.frames.enable_segments(compression);
+            }
+        }
+    }
+}
+
+// Closes the dispatcher however the task reading the responses ends, panics included, so that no
+// request waits forever for a response that cannot arrive
+struct CloseOnDrop(Arc<Mutex<Dispatcher>>);
+
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        let mut dispatcher = self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if dispatcher.closed.is_none() {
+            dispatcher.close(RCError::new("Connection reader stopped", ConnectionError));
+        }
+    }
+}
+
+// Writes the queued requests in order. A failed write closes the connection, as the server cannot make
+// sense of what follows a partially written frame
+async fn write_requests(mut writer: OwnedWriteHalf, mut requests: mpsc::UnboundedReceiver<Vec<u8>>,
+                        dispatcher: Arc<Mutex<Dispatcher>>, reader: AbortHandle) {
+    while let Some(data) = requests.recv().await {
+        if let Err(err) = writer.write_all(&data).await {
+            dispatcher.lock().unwrap().close(RCError::new(format!("Error writing request -> {}", err), IOError));
+            reader.abort();
+            return
+        }
+    }
+}
+
+async fn read_responses(mut reader: FrameReader<OwnedReadHalf>, dispatcher: Arc<Mutex<Dispatcher>>) {
+    let _guard = CloseOnDrop(dispatcher.clone());
+    loop {
+        let decoded = {
+            let mut dispatcher = dispatcher.lock().unwrap();
+            reader.decode_frame(&mut dispatcher.result_meta)
+        };
+        let failed = match decoded {
+            Ok(Some(response)) => {
+                dispatcher.lock().unwrap().dispatch(response);
+                continue
+            },
+            Ok(None) => reader.fill().await.err(),
+            Err(err) => Some(err)
+        };
+        if let Some(err) = failed {
+            dispatcher.lock().unwrap().close(err);
+            return
+        }
+    }
+}
+
+pub async fn connect(ip: &str, port: u16, creds: Option<&Vec<CowStr>>) -> RCResult<AsyncClient> {
+    connect_with_compression(ip, port, creds, &[]).await
+}
+
+///
+/// Connects and negotiates the protocol version the same way `client::connect_with_compression` does.
+/// Must be called from within a tokio runtime, which runs the task reading the responses.
+///
+pub async fn connect_with_compression(ip: &str, port: u16, creds: Option<&Vec<CowStr>>, compression: &[Compression]) -> RCResult<AsyncClient> {
+    let mut options = ConnectOptions::new();
+    options.compression = compression.to_vec();
+    connect_with_options(ip, port, creds, &options).await
+}
+
+///
+/// Connects with the given `options`, see `client::connect_with_options`. The request timeout of the
+/// options is the default one of the client.
+///
+pub async fn connect_with_options(ip: &str, port: u16, creds: Option<&Vec<CowStr>>, options: &ConnectOptions) -> RCResult<AsyncClient> {
+    let mut transport = AsyncTransport { ip: ip, port: port, options: options, connection: None };
+    let supported = try_rc!(handshake(&mut transport, creds, &options.compression).await, "Error connecting");
+    let (reader, writer) = match transport.connection {
+        Some(connection) => connection,
+        None => return Err(RCError::new("Not connected", ConnectionError))
+    };
+
+    let dispatcher = Arc::new(Mutex::new(Dispatcher::new(options.max_abandoned_streams)));
+    let (version, compression, segments) = (reader.version, reader.compression, reader.frames.has_segments());
+    let reader = tokio::spawn(read_responses(reader, dispatcher.clone())).abort_handle();
+    let (requests, queued) = mpsc::unbounded_channel();
+    tokio::spawn(write_requests(writer, queued, dispatcher.clone(), reader));
+    Ok(AsyncClient {
+        requests: requests,
+        dispatcher: dispatcher,
+        segments: segments,
+        version: version,
+        compression: compression,
+        supported: supported,
+        skip_metadata: false,
+        request_timeout: options.request_timeout,
+        serial_consistency: None,
+        timestamp_generator: Some(Box::new(MonotonicTimestampGenerator::new()))
+    })
+}
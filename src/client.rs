@@ -2,46 +2,388 @@ extern crate std;
 extern crate num;
 extern crate uuid;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::borrow::Cow;
-use std::path::Path;
-use std::error::Error;
+use std::io::{Read, Write, ErrorKind};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use self::uuid::Uuid;
 
 use super::def::*;
 use super::def::OpcodeRequest::*;
 use super::def::CqlRequestBody::*;
 use super::def::RCErrorType::*;
 use super::def::CqlResponseBody::*;
+use super::def::CqlValue::*;
 use super::serialize::CqlSerializable;
-use super::reader::*;
+use super::frame::FrameBuffer;
+use super::segment::encode_segments;
+use super::compression::compress;
+use super::timestamp::{TimestampGenerator, MonotonicTimestampGenerator};
+use super::handshake::{Transport, handshake, block_on};
 
-pub static CQL_VERSION_STRINGS:  [&'static str; 3] = ["3.0.0", "3.0.0", "3.0.0"];
-pub static CQL_MAX_SUPPORTED_VERSION:u8 = 0x03;
+pub static CQL_DEFAULT_VERSION: &str = "3.0.0";
+pub static CQL_MAX_SUPPORTED_VERSION:u8 = 0x05;
+
+pub static DEFAULT_MAX_ABANDONED_STREAMS: usize = 1024;
+
+static TRACE_FETCH_ATTEMPTS: u32 = 5;
+static TRACE_FETCH_DELAY_MS: u64 = 3;
 
 type PrepsStore = BTreeMap<String, Box<CqlPreparedStat>>;
 
+///
+/// Allocates stream ids for the requests in flight on a connection. Protocol v1 and v2 frames
+/// carry a signed byte (128 streams), v3 and later a signed short (32768 streams).
+///
+/// A stream whose response timed out is abandoned: it stays allocated until the late response arrives, as
+/// that response would otherwise be taken for the one of the next request on the stream. A server that
+/// never answers would leak such streams, so at most `max_abandoned` of them are allowed (and never more
+/// than half the streams of the protocol version).
+///
+pub struct StreamIds {
+    in_flight: BTreeSet<i16>,
+    abandoned: BTreeSet<i16>,
+    max_abandoned: usize,
+    next: i32
+}
+
+impl StreamIds {
+    pub fn new(max_abandoned: usize) -> StreamIds {
+        StreamIds { in_flight: BTreeSet::new(), abandoned: BTreeSet::new(), max_abandoned: max_abandoned, next: 0 }
+    }
+
+    pub fn max_streams(version: u8) -> i32 {
+        if version >= 3 { 32768 } else { 128 }
+    }
+
+    pub fn alloc(&mut self, version: u8) -> RCResult<i16> {
+        let max = StreamIds::max_streams(version);
+        if self.in_flight.len() as i32 >= max {
+            return Err(RCError::new(format!("All {} streams are in use", max), GenericError))
+        }
+        loop {
+            let stream = (self.next % max) as i16;
+            self.next = (self.next + 1) % max;
+            if self.in_flight.insert(stream) {
+                return Ok(stream)
+            }
+        }
+    }
+
+    pub fn release(&mut self, stream: i16) {
+        self.in_flight.remove(&stream);
+        self.abandoned.remove(&stream);
+    }
+
+    pub fn is_in_flight(&self, stream: i16) -> bool {
+        self.in_flight.contains(&stream)
+    }
+
+    ///
+    /// Marks a stream in flight as abandoned. Fails with a `ConnectionError` once too many streams are
+    /// abandoned, the connection should not be used any more then.
+    ///
+    pub fn abandon(&mut self, stream: i16, version: u8) -> RCResult<()> {
+        if !self.in_flight.contains(&stream) {
+            return Ok(())
+        }
+        self.abandoned.insert(stream);
+        let max = std::cmp::min(self.max_abandoned, StreamIds::max_streams(version) as usize / 2);
+        if self.abandoned.len() > max {
+            return Err(RCError::new(format!("Too many requests timed out without response ({})", self.abandoned.len()), ConnectionError))
+        }
+        Ok(())
+    }
+
+    /// Waits again for the response on an abandoned stream
+    pub fn reclaim(&mut self, stream: i16) {
+        self.abandoned.remove(&stream);
+    }
+
+    pub fn is_abandoned(&self, stream: i16) -> bool {
+        self.abandoned.contains(&stream)
+    }
+}
+
 pub struct Client {
-    socket: std::net::TcpStream,
+    socket: TcpStream,
+    frames: FrameBuffer,
     pub version: u8,
-    prepared: PrepsStore
+    pub compression: Compression,
+    pub supported: CqlStringMultiMap,
+    /// Ask the server not to send the result metadata of prepared statements, the one returned by PREPARE is used instead.
+    /// Off by default: before protocol v5 the server cannot tell that a schema change made that metadata stale, and
+    /// rows are then decoded with the wrong columns. From v5 on, pass Rows results to `CqlPreparedStat::update_result_metadata`
+    pub skip_metadata: bool,
+    /// Serial consistency of the conditional updates (lightweight transactions) sent by this client
+    pub serial_consistency: Option<Consistency>,
+    /// Maximum time to wait for the response to a request, or forever if None
+    pub request_timeout: Option<Duration>,
+    timestamp_generator: Option<Box<dyn TimestampGenerator + Send>>,
+    events: Option<Sender<CqlEvent>>,
+    streams: StreamIds,
+    pending: BTreeMap<i16, CqlResponse>,
+    result_meta: BTreeMap<i16, CqlMetadata>,
+    prepared: PrepsStore,
+    defunct: Option<CowStr>
 }
 
 impl Client {
 
-    fn new(socket: std::net::TcpStream, version: u8) -> Client {
-        Client {socket: socket, version: version, prepared: BTreeMap::new()}
+    fn new(socket: TcpStream, version: u8) -> Client {
+        Client {socket: socket, frames: FrameBuffer::new(), version: version, compression: Compression::None,
+                supported: CqlStringMultiMap::new(), skip_metadata: false, serial_consistency: None, request_timeout: None,
+                timestamp_generator: Some(Box::new(MonotonicTimestampGenerator::new())), events: None,
+                streams: StreamIds::new(DEFAULT_MAX_ABANDONED_STREAMS), pending: BTreeMap::new(),
+                result_meta: BTreeMap::new(), prepared: BTreeMap::new(), defunct: None}
+    }
+
+    // The request is sent on a newly allocated stream, which is returned so the response can be
+    // claimed with `read_response`
+    fn send_request<'a>(&mut self, request: &mut CqlRequest<'a>) -> RCResult<i16> {
+        try_rc!(self.check_defunct(), "Error sending request");
+        request.stream = try_rc!(self.streams.alloc(self.version), "Error allocating stream");
+        match self.write_request(request) {
+            Ok(_) => Ok(request.stream),
+            Err(err) => {
+                self.streams.release(request.stream);
+                Err(err)
+            }
+        }
+    }
+
+    // A request that fails or times out while being written leaves the connection unusable, as the
+    // server would read the next request as the rest of this one
+    fn write_request<'a>(&mut self, request: &CqlRequest<'a>) -> RCResult<()> {
+        let data = try_rc!(encode_request(request, self.version, self.compression, self.frames.has_segments()), "Error encoding request");
+        try_io!(self.socket.set_write_timeout(self.request_timeout), "Error setting write timeout");
+        let err = match self.socket.write_all(&data) {
+            Ok(_) => return Ok(()),
+            Err(ref err) if is_timeout(err) => RCError::new("Timed out writing request", TimeoutError),
+            Err(err) => RCError::new(format!("Error writing request -> {}", err), IOError)
+        };
+        Err(self.set_defunct(err))
+    }
+
+    // Once defunct, every request fails right away with a `ConnectionError`
+    fn set_defunct(&mut self, err: RCError) -> RCError {
+        self.defunct = Some(err.desc.clone());
+        err
+    }
+
+    fn check_defunct(&self) -> RCResult<()> {
+        match self.defunct {
+            Some(ref desc) => Err(RCError::new(format!("Connection is defunct: {}", desc), ConnectionError)),
+            None => Ok(())
+        }
+    }
+
+    ///
+    /// Whether the connection failed and can no longer be used, e.g. after a request could not be written.
+    /// A request whose response timed out does not make the connection defunct.
+    ///
+    pub fn is_defunct(&self) -> bool {
+        self.defunct.is_some()
+    }
+
+    // Responses to other requests in flight are kept until they are claimed. Events pushed by
+    // the server (stream -1) can arrive at any time, they are dispatched to the registered channel.
+    // If the response does not arrive in time the stream is abandoned: it is not reused until the
+    // late response arrives, and that one is dropped. Too many abandoned streams make the connection defunct
+    fn read_response(&mut self, stream: i16) -> RCResult<CqlResponse> {
+        if let Some(response) = self.pending.remove(&stream) {
+            self.streams.release(stream);
+            return Ok(response)
+        }
+        if !self.streams.is_in_flight(stream) {
+            return Err(RCError::new(format!("No request in flight on stream {}", stream), GenericError))
+        }
+        // Waiting again for a response that timed out claims it back
+        self.streams.reclaim(stream);
+        let deadline = self.request_timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let response = match self.read_frame(deadline) {
+                Ok(response) => response,
+                Err(err) => {
+                    if err.kind == TimeoutError {
+                        if let Err(abandoned) = self.streams.abandon(stream, self.version) {
+                            self.set_defunct(abandoned);
+                        }
+                    }
+                    return Err(RCError::new(format!("Error reading response frame -> {}", err.desc), err.kind))
+                }
+            };
+            if response.stream == stream {
+                self.streams.release(stream);
+                return Ok(response)
+            }
+            self.dispatch_response(response);
+        }
+    }
+
+    fn dispatch_response(&mut self, response: CqlResponse) {
+        if response.stream < 0 {
+            self.dispatch_event(response);
+        } else if self.streams.is_abandoned(response.stream) {
+            self.streams.release(response.stream);
+        } else if self.streams.is_in_flight(response.stream) {
+            self.pending.insert(response.stream, response);
+        }
+        // Responses on streams nobody is waiting for are dropped
+    }
+
+    fn dispatch_event(&mut self, response: CqlResponse) {
+        if let ResponseEvent(event) = response.body {
+            let delivered = match self.events {
+                Some(ref sender) => sender.send(event).is_ok(),
+                None => false
+            };
+            if !delivered {
+                self.events = None;
+            }
+        }
     }
 
-    fn build_options(&self) -> CqlRequest {
+    // Reads until a whole frame is buffered or the deadline passes. A partially received frame stays
+    // buffered, so the connection can still be used after a timeout
+    fn read_frame(&mut self, deadline: Option<Instant>) -> RCResult<CqlResponse> {
+        try_rc!(self.check_defunct(), "Error reading frame");
+        let mut chunk = [0u8; 4096];
+        loop {
+            let decoded = match self.frames.next_frame(self.version, &mut self.result_meta) {
+                Ok(decoded) => decoded,
+                Err(err) => return Err(self.set_defunct(RCError::new(format!("Error reading frame -> {}", err.desc), err.kind)))
+            };
+            if let Some(response) = decoded {
+                return Ok(response)
+            }
+            let timeout = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RCError::new("Timed out waiting for response", TimeoutError))
+                    }
+                    Some(deadline - now)
+                },
+                None => None
+            };
+            try_io!(self.socket.set_read_timeout(timeout), "Error setting read timeout");
+            let n = match self.socket.read(&mut chunk) {
+                Ok(n) => n,
+                Err(ref err) if is_timeout(err) => return Err(RCError::new("Timed out waiting for response", TimeoutError)),
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(self.set_defunct(RCError::new(format!("Error reading from socket -> {}", err), IOError)))
+            };
+            if n == 0 {
+                return Err(self.set_defunct(RCError::new("Connection closed by the server", ConnectionError)))
+            }
+            if let Err(err) = self.frames.push(&chunk[..n]) {
+                return Err(self.set_defunct(RCError::new(format!("Error reading frame -> {}", err.desc), err.kind)))
+            }
+        }
+    }
+
+    // Default timestamps were introduced in protocol v3
+    fn default_timestamp(&self) -> Option<i64> {
+        match self.timestamp_generator {
+            Some(ref generator) if self.version >= 3 => Some(generator.next_timestamp()),
+            _ => None
+        }
+    }
+
+    fn build_options(&self) -> CqlRequest<'static> {
         return CqlRequest {
-            version: self.version,
             flags: 0x00,
-            stream: 0x01,
+            stream: 0x00,
             opcode: OpcodeOptions,
             body: RequestOptions,
         };
     }
 
+    ///
+    /// Sends an OPTIONS request and returns the options the server supports (CQL_VERSION, COMPRESSION, ...)
+    ///
+    pub fn options(&mut self) -> RCResult<CqlStringMultiMap> {
+        let mut q = self.build_options();
+        let stream = try_rc!(self.send_request(&mut q), "Error serializing options");
+        let res = try_rc!(self.read_response(stream), "Error reading options");
+        match res.body {
+            ResponseSupported(supported) => Ok(supported),
+            ResponseError(_, ref msg) => Err(RCError::new(format!("Error reading options: {}", msg), ReadError)),
+            _ => Err(RCError::new("Response does not contain supported options", ReadError))
+        }
+    }
+
+    ///
+    /// Subscribes to server pushed events. Events are delivered through the returned channel
+    /// whenever the client reads from the connection, see `process_events`.
+    ///
+    pub fn register(&mut self, events: &[CqlEventType]) -> RCResult<Receiver<CqlEvent>> {
+        let mut q = CqlRequest {
+            flags: 0x00,
+            stream: 0x00,
+            opcode: OpcodeRegister,
+            body: RequestRegister(events),
+        };
+
+        let stream = try_rc!(self.send_request(&mut q), "Error serializing register");
+        let res = try_rc!(self.read_response(stream), "Error reading register");
+        match res.body {
+            ResponseReady => {
+                let (sender, receiver) = channel();
+                self.events = Some(sender);
+                Ok(receiver)
+            },
+            ResponseError(_, ref msg) => Err(RCError::new(format!("Error registering for events: {}", msg), ReadError)),
+            _ => Err(RCError::new("Wrong response to register", ReadError))
+        }
+    }
+
+    ///
+    /// Blocks until the server pushes the next event and dispatches it. Meant for connections
+    /// dedicated to listening to events.
+    ///
+    pub fn process_events(&mut self) -> RCResult<()> {
+        let response = try_rc!(self.read_frame(None), "Error reading event");
+        self.dispatch_response(response);
+        Ok(())
+    }
+
+    ///
+    /// Waits for the response to a request sent with `send_query` or `send_prepared`. Responses
+    /// can be claimed in any order.
+    ///
+    pub fn get_response(&mut self, stream: i16) -> RCResult<CqlResponse> {
+        self.read_response(stream)
+    }
+
+    ///
+    /// Runs `f` with `timeout` as request timeout instead of `request_timeout`.
+    ///
+    #[deprecated(note = "set `QueryOptions::timeout` instead")]
+    pub fn with_timeout<T, F>(&mut self, timeout: Option<Duration>, f: F) -> T
+        where F: FnOnce(&mut Client) -> T {
+        self.with_request_timeout(timeout, f)
+    }
+
+    fn with_request_timeout<T, F>(&mut self, timeout: Option<Duration>, f: F) -> T
+        where F: FnOnce(&mut Client) -> T {
+        let default = std::mem::replace(&mut self.request_timeout, timeout);
+        let result = f(self);
+        self.request_timeout = default;
+        result
+    }
+
+    ///
+    /// Replaces the generator of the timestamps sent with QUERY, EXECUTE and BATCH requests. With no generator
+    /// the server assigns the timestamps.
+    ///
+    pub fn set_timestamp_generator(&mut self, generator: Option<Box<dyn TimestampGenerator + Send>>) {
+        self.timestamp_generator = generator;
+    }
+
     pub fn get_prepared_statement(&mut self, ps_id: &str) -> RCResult<&CqlPreparedStat> {
         match self.prepared.get(ps_id) {
             Some(ps) => Ok(&**ps),
@@ -49,84 +391,300 @@ impl Client {
         }
     }
 
-    pub fn exec_query(&mut self, query_str: &str, con: Consistency) -> RCResult<CqlResponse> {
-        let q = CqlRequest {
-            version: self.version,
-            flags: 0x00,
-            stream: 0x01,
+    ///
+    /// Executes a query. `values` are bound to the `?` markers of the query (protocol v2 or later).
+    /// `options` are either a `Consistency` or `QueryOptions`, as for every other execution method.
+    ///
+    pub fn exec_query<O: Into<QueryOptions>>(&mut self, query_str: &str, values: &[CqlValue], options: O) -> RCResult<CqlResponse> {
+        let options = options.into();
+        self.query_with_options(query_str, options.query_params(values), &options)
+    }
+
+    ///
+    /// Executes a query with explicit parameters. The serial consistency and timestamp of the client are
+    /// used unless they are set in `params`.
+    ///
+    #[deprecated(note = "use `exec_query` with `QueryOptions`, or `exec_query_named` for named values")]
+    pub fn exec_query_params(&mut self, query_str: &str, params: QueryParams) -> RCResult<CqlResponse> {
+        let options = QueryOptions::new(params.consistency);
+        self.query_with_options(query_str, params, &options)
+    }
+
+    ///
+    /// Executes a query binding the values by name (protocol v3 or later).
+    ///
+    pub fn exec_query_named<O: Into<QueryOptions>>(&mut self, query_str: &str, values: &[(&str, CqlValue)], options: O) -> RCResult<CqlResponse> {
+        let options = options.into();
+        let mut params = options.query_params(&[]);
+        params.named_values = values;
+        self.query_with_options(query_str, params, &options)
+    }
+
+    ///
+    /// Fetches a single page of at most `page_size` rows. The paging state of the next page, if there
+    /// is one, is returned in the metadata of the rows.
+    ///
+    #[deprecated(note = "use `exec_query` with `QueryOptions::page_size` and `QueryOptions::paging_state`")]
+    pub fn exec_query_page<O: Into<QueryOptions>>(&mut self, query_str: &str, values: &[CqlValue], options: O,
+                                                  page_size: i32, paging_state: Option<Vec<u8>>) -> RCResult<CqlResponse> {
+        let mut options = options.into();
+        options.page_size = Some(page_size);
+        options.paging_state = paging_state;
+        self.exec_query(query_str, values, options)
+    }
+
+    ///
+    /// Iterates over all the rows of the query, fetching the pages of `page_size` rows as they are needed.
+    ///
+    pub fn query_iter<'c, O: Into<QueryOptions>>(&'c mut self, query_str: &str, values: &'c [CqlValue], options: O, page_size: i32) -> RowIter<'c> {
+        RowIter {
+            client: self,
+            query: query_str.to_owned(),
+            values: values,
+            options: options.into(),
+            page_size: page_size,
+            paging_state: None,
+            rows: vec![].into_iter(),
+            done: false
+        }
+    }
+
+    ///
+    /// Sends a query without waiting for its response, so several requests can be in flight on the
+    /// connection. Returns the stream to pass to `get_response`.
+    ///
+    pub fn send_query<O: Into<QueryOptions>>(&mut self, query_str: &str, values: &[CqlValue], options: O) -> RCResult<i16> {
+        let options = options.into();
+        self.send_query_with_options(query_str, options.query_params(values), &options)
+    }
+
+    ///
+    /// Executes the query with tracing enabled. The response carries the id of the tracing session,
+    /// which can be fetched afterwards with `get_trace_session`.
+    ///
+    #[deprecated(note = "use `exec_query` with `QueryOptions::tracing`")]
+    pub fn exec_query_traced<O: Into<QueryOptions>>(&mut self, query_str: &str, values: &[CqlValue], options: O) -> RCResult<CqlResponse> {
+        let mut options = options.into();
+        options.tracing = true;
+        self.exec_query(query_str, values, options)
+    }
+
+    fn query_with_options(&mut self, query_str: &str, mut params: QueryParams, options: &QueryOptions) -> RCResult<CqlResponse> {
+        if params.timestamp.is_none() {
+            params.timestamp = self.default_timestamp();
+        }
+        let timeout = options.timeout.or(self.request_timeout);
+        self.with_request_timeout(timeout, |client| client.retry_idempotent(options, |client| {
+            let stream = try_rc!(client.send_query_with_options(query_str, params.clone(), options), "Error sending query");
+            Ok(try_rc!(client.read_response(stream), "Error reading query"))
+        }))
+    }
+
+    // Idempotent statements are sent once more when their response times out. Their timestamp is set
+    // beforehand, so the second attempt applies as the first one would have
+    fn retry_idempotent<F>(&mut self, options: &QueryOptions, mut f: F) -> RCResult<CqlResponse>
+        where F: FnMut(&mut Client) -> RCResult<CqlResponse> {
+        match f(self) {
+            Err(ref err) if err.kind == TimeoutError && options.idempotent && !self.is_defunct() => f(self),
+            response => response
+        }
+    }
+
+    fn send_query_with_options(&mut self, query_str: &str, mut params: QueryParams, options: &QueryOptions) -> RCResult<i16> {
+        if params.serial_consistency.is_none() {
+            params.serial_consistency = self.serial_consistency;
+        }
+        if params.timestamp.is_none() {
+            params.timestamp = self.default_timestamp();
+        }
+        let mut q = CqlRequest {
+            flags: if options.tracing { 0x02 } else { 0x00 },
+            stream: 0x00,
             opcode: OpcodeQuery,
-            body: RequestQuery(query_str, con, 0)};
+            body: RequestQuery(query_str, params)};
 
-        let mut socket = try_io!(self.socket.try_clone(), "Cannot clone tcp handle");
-        try_rc!(q.serialize(&mut socket, self.version), "Error serializing query");
-        Ok(try_rc!(socket.read_cql_response(self.version), "Error reading query"))
+        let timeout = options.timeout.or(self.request_timeout);
+        Ok(try_rc!(self.with_request_timeout(timeout, |client| client.send_request(&mut q)), "Error serializing query"))
     }
 
-    pub fn exec_prepared(&mut self, preps: &Vec<u8>, params: &[CqlValue], con: Consistency) -> RCResult<CqlResponse> {
+    pub fn exec_prepared<O: Into<QueryOptions>>(&mut self, preps: &CqlPreparedStat, params: &[CqlValue], options: O) -> RCResult<CqlResponse> {
+        let options = options.into();
+        self.execute_with_options(preps, options.query_params(params), &options)
+    }
 
-        let q = CqlRequest {
-            version: self.version,
-            flags: 0x00,
-            stream: 0x01,
+    ///
+    /// Executes a prepared statement with explicit parameters, see `exec_query_params`.
+    ///
+    #[deprecated(note = "use `exec_prepared` with `QueryOptions`, or `exec_prepared_named` for named values")]
+    pub fn exec_prepared_params(&mut self, preps: &CqlPreparedStat, params: QueryParams) -> RCResult<CqlResponse> {
+        let options = QueryOptions::new(params.consistency);
+        self.execute_with_options(preps, params, &options)
+    }
+
+    ///
+    /// Executes a prepared statement binding the values by the names of its markers (protocol v3 or later).
+    ///
+    pub fn exec_prepared_named<O: Into<QueryOptions>>(&mut self, preps: &CqlPreparedStat, values: &[(&str, CqlValue)], options: O) -> RCResult<CqlResponse> {
+        let options = options.into();
+        let mut params = options.query_params(&[]);
+        params.named_values = values;
+        self.execute_with_options(preps, params, &options)
+    }
+
+    ///
+    /// Executes a prepared statement fetching a single page of results, see `exec_query_page`.
+    ///
+    #[deprecated(note = "use `exec_prepared` with `QueryOptions::page_size` and `QueryOptions::paging_state`")]
+    pub fn exec_prepared_page<O: Into<QueryOptions>>(&mut self, preps: &CqlPreparedStat, params: &[CqlValue], options: O,
+                                                     page_size: i32, paging_state: Option<Vec<u8>>) -> RCResult<CqlResponse> {
+        let mut options = options.into();
+        options.page_size = Some(page_size);
+        options.paging_state = paging_state;
+        self.exec_prepared(preps, params, options)
+    }
+
+    ///
+    /// Sends a prepared statement execution without waiting for its response, see `send_query`.
+    ///
+    pub fn send_prepared<O: Into<QueryOptions>>(&mut self, preps: &CqlPreparedStat, params: &[CqlValue], options: O) -> RCResult<i16> {
+        let options = options.into();
+        self.send_execute_with_options(preps, options.query_params(params), &options)
+    }
+
+    #[deprecated(note = "use `exec_prepared` with `QueryOptions::tracing`")]
+    pub fn exec_prepared_traced<O: Into<QueryOptions>>(&mut self, preps: &CqlPreparedStat, params: &[CqlValue], options: O) -> RCResult<CqlResponse> {
+        let mut options = options.into();
+        options.tracing = true;
+        self.exec_prepared(preps, params, options)
+    }
+
+    fn execute_with_options(&mut self, preps: &CqlPreparedStat, mut params: QueryParams, options: &QueryOptions) -> RCResult<CqlResponse> {
+        if params.timestamp.is_none() {
+            params.timestamp = self.default_timestamp();
+        }
+        let timeout = options.timeout.or(self.request_timeout);
+        self.with_request_timeout(timeout, |client| client.retry_idempotent(options, |client| {
+            let stream = try_rc!(client.send_execute_with_options(preps, params.clone(), options), "Error sending prepared statement execution");
+            Ok(try_rc!(client.read_response(stream), "Error reading prepared statement execution result"))
+        }))
+    }
+
+    // The keyspace of a prepared statement is the one it was prepared in
+    fn send_execute_with_options(&mut self, preps: &CqlPreparedStat, mut params: QueryParams, options: &QueryOptions) -> RCResult<i16> {
+        let result_meta = match preps.meta_result {
+            Some(ref meta) if self.skip_metadata && meta.column_count > 0 => Some(meta.clone()),
+            _ => None
+        };
+        params.skip_metadata = result_meta.is_some();
+        params.keyspace = None;
+        if params.serial_consistency.is_none() {
+            params.serial_consistency = self.serial_consistency;
+        }
+        if params.timestamp.is_none() {
+            params.timestamp = self.default_timestamp();
+        }
+        let mut q = CqlRequest {
+            flags: if options.tracing { 0x02 } else { 0x00 },
+            stream: 0x00,
             opcode: OpcodeExecute,
-            body: RequestExec(preps.clone(), params, con, 0x01),
+            body: RequestExec(preps, params),
         };
 
-        let mut socket = try_io!(self.socket.try_clone(), "Cannot clone tcp handle");
-        try_rc!(q.serialize(&mut socket, self.version), "Error serializing prepared statement execution");
+        let timeout = options.timeout.or(self.request_timeout);
+        let stream = try_rc!(self.with_request_timeout(timeout, |client| client.send_request(&mut q)), "Error serializing prepared statement execution");
+        match result_meta {
+            Some(meta) => { self.result_meta.insert(stream, meta); },
+            None => { self.result_meta.remove(&stream); }
+        }
 
-        // Code to debug prepared statements. Write to file the serialization of the request
-        let path = Path::new("prepared_data.bin");
-        let display = path.display();
-        let mut file = match std::fs::File::create(&path) {
-            Err(err) => panic!("couldn't create {}: {}", display, err.description()),
-            Ok(file) => file,
-        };
-        try_rc!(q.serialize(&mut file, self.version), "Error serializing query to file");
+        Ok(stream)
+    }
 
-        Ok(try_rc!(socket.read_cql_response(self.version), "Error reading prepared statement execution result"))
+    pub fn exec_batch<O: Into<QueryOptions>>(&mut self, q_type: BatchType, q_vec: Vec<Query>, options: O) -> RCResult<CqlResponse> {
+        let options = options.into();
+        let params = options.batch_params();
+        self.batch_with_options(q_type, q_vec, params, &options)
     }
 
-    pub fn exec_batch(&mut self, q_type: BatchType, q_vec: Vec<Query>, con: Consistency) -> RCResult<CqlResponse> {
-        let q = CqlRequest {
-            version: self.version,
-            flags: 0x00,
-            stream: 0x01,
+    ///
+    /// Executes a batch with explicit parameters, see `exec_query_params`.
+    ///
+    #[deprecated(note = "use `exec_batch` with `QueryOptions`")]
+    pub fn exec_batch_params(&mut self, q_type: BatchType, q_vec: Vec<Query>, params: BatchParams) -> RCResult<CqlResponse> {
+        let options = QueryOptions::new(params.consistency);
+        self.batch_with_options(q_type, q_vec, params, &options)
+    }
+
+    fn batch_with_options(&mut self, q_type: BatchType, q_vec: Vec<Query>, mut params: BatchParams, options: &QueryOptions) -> RCResult<CqlResponse> {
+        if params.serial_consistency.is_none() {
+            params.serial_consistency = self.serial_consistency;
+        }
+        if params.timestamp.is_none() {
+            params.timestamp = self.default_timestamp();
+        }
+        let mut q = CqlRequest {
+            flags: if options.tracing { 0x02 } else { 0x00 },
+            stream: 0x00,
             opcode: OpcodeBatch,
-            body: RequestBatch(q_vec, q_type, con, 0)};
+            body: RequestBatch(q_vec, q_type, params)};
+
+        let timeout = options.timeout.or(self.request_timeout);
+        self.with_request_timeout(timeout, |client| client.retry_idempotent(options, |client| {
+            let stream = try_rc!(client.send_request(&mut q), "Error serializing BATCH request");
+            let res = try_rc!(client.read_response(stream), "Error reading query");
+            Ok(res)
+        }))
+    }
 
-        /* Code to debug batch statements. Write to file the serialization of the request
 
-        let path = Path::new("batch_data.bin");
-        let display = path.display();
-        let mut file = match std::old_io::File::create(&path) {
-            Err(why) => panic!("couldn't create {}: {}", display, why.desc),
-            Ok(file) => file,
+    ///
+    /// Fetches a tracing session and its events from `system_traces`. Traces are written asynchronously
+    /// by the server, so the session is polled a few times until it is complete.
+    ///
+    pub fn get_trace_session(&mut self, session_id: &Uuid) -> RCResult<TraceSession> {
+        let session_query = format!("SELECT coordinator, duration, request, started_at FROM system_traces.sessions WHERE session_id = {}", session_id);
+        let events_query = format!("SELECT event_id, activity, source, source_elapsed, thread FROM system_traces.events WHERE session_id = {}", session_id);
+
+        let mut session = None;
+        for attempt in 0 .. TRACE_FETCH_ATTEMPTS {
+            let res = try_rc!(self.exec_query(&session_query, &[], Consistency::One), "Error reading trace session");
+            session = match res.body {
+                ResultRows(rows) => rows.rows.into_iter().next().and_then(|row| trace_session_from_row(session_id, row)),
+                ResponseError(_, ref msg) => return Err(RCError::new(format!("Error reading trace session: {}", msg), ReadError)),
+                _ => return Err(RCError::new("Trace session response does not contain rows", ReadError))
+            };
+            if session.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(TRACE_FETCH_DELAY_MS << attempt));
+        }
+        let mut session = match session {
+            Some(session) => session,
+            None => return Err(RCError::new(format!("Trace session {} is not available", session_id), NoDataError))
         };
 
-        serialize_and_check_io_error!(serialize_with_client, &mut file, q, self, "Error serializing to file");
-        */
-
-        let mut socket = try_io!(self.socket.try_clone(), "Cannot clone tcp handle");
-        try_rc!(q.serialize(&mut socket, self.version), "Error serializing BATCH request");
-        let res = try_rc!(socket.read_cql_response(self.version), "Error reading query");
-        Ok(res)
+        let res = try_rc!(self.exec_query(&events_query, &[], Consistency::One), "Error reading trace events");
+        match res.body {
+            ResultRows(rows) => {
+                session.events = rows.rows.into_iter().filter_map(trace_event_from_row).collect();
+                Ok(session)
+            },
+            ResponseError(_, ref msg) => Err(RCError::new(format!("Error reading trace events: {}", msg), ReadError)),
+            _ => Err(RCError::new("Trace events response does not contain rows", ReadError))
+        }
     }
 
-
     pub fn prepared_statement(&mut self, query_str: &str) -> RCResult<CqlPreparedStat> {
-        let q = CqlRequest {
-            version: self.version,
+        let mut q = CqlRequest {
             flags: 0x00,
-            stream: 0x01,
+            stream: 0x00,
             opcode: OpcodePrepare,
             body: RequestPrepare(query_str),
         };
 
-        let mut socket = try_io!(self.socket.try_clone(), "Cannot clone tcp handle");
-        try_rc!(q.serialize(&mut socket, self.version), "Error serializing prepared statement");
+        let stream = try_rc!(self.send_request(&mut q), "Error serializing prepared statement");
 
-        let res = try_rc!(socket.read_cql_response(self.version), "Error reading query");
+        let res = try_rc!(self.read_response(stream), "Error reading query");
         match res.body {
             ResultPrepared(preps) => {
                 Ok(preps)
@@ -136,90 +694,321 @@ impl Client {
     }
 }
 
-fn approve_authenticator(authenticator: &CowStr) -> bool {
-    authenticator == "org.apache.cassandra.auth.PasswordAuthenticator"
+///
+/// Serializes a request as it is sent on the wire: wrapped in segments, or with its body compressed.
+///
+pub(crate) fn encode_request<'a>(request: &CqlRequest<'a>, version: u8, compression: Compression, segments: bool) -> RCResult<Vec<u8>> {
+    let mut data: Vec<u8> = Vec::with_capacity(request.len(version));
+    try_rc!(request.serialize(&mut data, version), "Error serializing request");
+    if segments {
+        data = try_rc!(encode_segments(&data, compression), "Error encoding request segments");
+    } else if compression != Compression::None {
+        data = try_rc!(compress_frame(&data, version, compression), "Error compressing request");
+    }
+    Ok(data)
+}
+
+///
+/// Iterator over the rows of a query, see `Client::query_iter`. A failed page request is returned as
+/// an error and ends the iteration.
+///
+pub struct RowIter<'c> {
+    client: &'c mut Client,
+    query: String,
+    values: &'c [CqlValue],
+    options: QueryOptions,
+    page_size: i32,
+    paging_state: Option<Vec<u8>>,
+    rows: std::vec::IntoIter<CqlRow>,
+    done: bool
+}
+
+impl<'c> Iterator for RowIter<'c> {
+    type Item = RCResult<CqlRow>;
+
+    fn next(&mut self) -> Option<RCResult<CqlRow>> {
+        loop {
+            if let Some(row) = self.rows.next() {
+                return Some(Ok(row))
+            }
+            if self.done {
+                return None
+            }
+            let mut options = self.options.clone();
+            options.page_size = Some(self.page_size);
+            options.paging_state = self.paging_state.take();
+            let res = match self.client.exec_query(&self.query, self.values, options) {
+                Ok(res) => res,
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err))
+                }
+            };
+            match res.body {
+                ResultRows(rows) => {
+                    self.paging_state = rows.metadata.paging_state;
+                    self.done = self.paging_state.is_none();
+                    self.rows = rows.rows.into_iter();
+                },
+                ResponseError(_, ref msg) => {
+                    self.done = true;
+                    return Some(Err(RCError::new(format!("Error fetching page: {}", msg), ReadError)))
+                },
+                _ => {
+                    self.done = true;
+                    return Some(Err(RCError::new("Paged query did not return rows", ReadError)))
+                }
+            }
+        }
+    }
+}
+
+// Depending on the platform, a socket timeout is reported as either of these
+fn is_timeout(err: &std::io::Error) -> bool {
+    err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut
+}
+
+fn compress_frame(frame: &[u8], version: u8, compression: Compression) -> RCResult<Vec<u8>> {
+    let header_len = if version >= 3 { 9 } else { 8 };
+    let body = try_rc!(compress(&frame[header_len..], compression), "Error compressing frame body");
+    let len = body.len() as u32;
+
+    let mut data: Vec<u8> = Vec::with_capacity(header_len + body.len());
+    data.extend(&frame[.. header_len - 4]);
+    data[1] |= 0x01;
+    data.extend(&[(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8]);
+    data.extend(body);
+    Ok(data)
+}
+
+fn parse_version(version: &str) -> Vec<u32> {
+    version.split('.').map(|n| n.parse().unwrap_or(0)).collect()
+}
+
+///
+/// Highest CQL version advertised in CQL_VERSION, or the default one if the server sends none.
+///
+pub fn choose_cql_version(supported: &CqlStringMultiMap) -> CowStr {
+    match supported.get("CQL_VERSION").and_then(|versions| versions.iter().max_by_key(|v| parse_version(v))) {
+        Some(version) => version.clone(),
+        None => Cow::Borrowed(CQL_DEFAULT_VERSION)
+    }
+}
+
+///
+/// Highest non beta protocol version advertised in PROTOCOL_VERSIONS (e.g. "4/v4", "5/v5-beta") that
+/// is not greater than `max_version`. Older servers do not advertise their protocol versions.
+///
+pub fn choose_protocol_version(supported: &CqlStringMultiMap, max_version: u8) -> Option<u8> {
+    let versions = supported.get("PROTOCOL_VERSIONS")?;
+    versions.iter()
+        .filter(|v| !v.ends_with("-beta"))
+        .filter_map(|v| v.split('/').next().and_then(|n| n.parse::<u8>().ok()))
+        .filter(|v| *v <= max_version)
+        .max()
 }
 
 ///
-/// Makes an authentication response token that is compatible with PasswordAuthenticator.
+/// Picks the first of the preferred compression algorithms that the server advertises.
+/// Segments (protocol v5) can only be compressed with LZ4.
 ///
-fn make_token(creds: &Vec<CowStr>) -> Vec<u8> {
-    let mut token : Vec<u8> = Vec::new();
-    for cred in creds {
-        token.push(0);
-        token.extend(cred.as_bytes());
+pub fn choose_compression(supported: &CqlStringMultiMap, preferred: &[Compression], version: u8) -> Compression {
+    let advertised = match supported.get("COMPRESSION") {
+        Some(algorithms) => algorithms,
+        None => return Compression::None
+    };
+    for compression in preferred.iter() {
+        if version >= 5 && *compression != Compression::Lz4 {
+            continue;
+        }
+        if advertised.iter().any(|a| a == compression.as_str()) {
+            return *compression;
+        }
     }
-    return token;
+    Compression::None
 }
 
-fn send_startup(socket: &mut std::net::TcpStream, version: u8, creds: Option<&Vec<CowStr>>) -> RCResult<()> {
-    let body = CqlStringMap {
-        pairs:vec![CqlPair{key: "CQL_VERSION", value: CQL_VERSION_STRINGS[(version-1) as usize]}],
+// A session without duration is still being written by the server
+fn trace_session_from_row(session_id: &Uuid, row: CqlRow) -> Option<TraceSession> {
+    let mut cols = row.cols.into_iter();
+    let coordinator = match cols.next() {
+        Some(CqlInet(Some(ip))) => ip,
+        _ => return None
+    };
+    let duration = match cols.next() {
+        Some(CqlInt(Some(duration))) => duration,
+        _ => return None
+    };
+    let request = match cols.next() {
+        Some(CqlVarchar(Some(s))) | Some(CqlText(Some(s))) => s,
+        _ => Cow::Borrowed("")
     };
-    let msg_startup = CqlRequest {
-        version: version,
-        flags: 0x00,
-        stream: 0x01,
-        opcode: OpcodeStartup,
-        body: RequestStartup(body),
+    let started_at = match cols.next() {
+        Some(CqlTimestamp(Some(t))) => t,
+        _ => 0
     };
+    Some(TraceSession {
+        session_id: *session_id,
+        coordinator: coordinator,
+        duration: duration,
+        request: request,
+        started_at: started_at,
+        events: vec![]
+    })
+}
 
-    try_rc!(msg_startup.serialize(socket, version), "Error serializing startup message");
-
-    let response = try_rc!(socket.read_cql_response(version), "Error reading response");
-    match response.body {
-        ResponseReady =>  Ok(()),
-        ResponseAuthenticate(authenticator) => {
-            if approve_authenticator(&authenticator) {
-                match creds {
-                    Some(cred) => {
-                        if version >= 2 {
-                            let msg_auth = CqlRequest {
-                                version: version,
-                                flags: 0x00,
-                                stream: 0x01,
-                                opcode: OpcodeAuthResponse,
-                                body: RequestAuthResponse(make_token(cred)),
-                            };
-                            try_rc!(msg_auth.serialize(socket, version), "Error serializing request (auth)");
-                            let response = try_rc!(socket.read_cql_response(version), "Error reading authentication response");
-                            match response.body {
-                                ResponseAuthSuccess(_) => Ok(()),
-                                ResponseError(_, ref msg) => Err(RCError::new(format!("Error in authentication: {}", msg), ReadError)),
-                                _ => Err(RCError::new("Server returned unknown message", ReadError))
-                            }
-                        } else {
-                            Err(RCError::new("Authentication is not supported for v1 protocol", ReadError)) 
-                        }
-                    },
-                    None => Err(RCError::new("Credential should be provided for authentication", ReadError))
-                }
-            } else {
-                Err(RCError::new(format!("Unexpected authenticator: {}", authenticator), ReadError))
-            }
-        },
-        ResponseError(_, ref msg) => Err(RCError::new(format!("Error connecting: {}", msg), ReadError)),
-        _ => Err(RCError::new("Wrong response to startup", ReadError))
+fn trace_event_from_row(row: CqlRow) -> Option<TraceEvent> {
+    let mut cols = row.cols.into_iter();
+    let event_id = match cols.next() {
+        Some(CqlTimeUuid(Some(u))) => u,
+        _ => return None
+    };
+    let activity = match cols.next() {
+        Some(CqlVarchar(Some(s))) | Some(CqlText(Some(s))) => s,
+        _ => Cow::Borrowed("")
+    };
+    let source = match cols.next() {
+        Some(CqlInet(Some(ip))) => ip,
+        _ => return None
+    };
+    let source_elapsed = match cols.next() {
+        Some(CqlInt(Some(elapsed))) => elapsed,
+        _ => 0
+    };
+    let thread = match cols.next() {
+        Some(CqlVarchar(Some(s))) | Some(CqlText(Some(s))) => s,
+        _ => Cow::Borrowed("")
+    };
+    Some(TraceEvent {
+        event_id: event_id,
+        activity: activity,
+        source: source,
+        source_elapsed: source_elapsed,
+        thread: thread
+    })
+}
+
+///
+/// Settings of a new connection. By default no compression is used and there are no timeouts.
+///
+pub struct ConnectOptions {
+    /// Algorithms to try, in order of preference
+    pub compression: Vec<Compression>,
+    /// Maximum time to establish the TCP connection
+    pub connect_timeout: Option<Duration>,
+    /// Default maximum time to wait for a response, including the ones of the connection handshake
+    pub request_timeout: Option<Duration>,
+    /// Number of requests whose response timed out and still has not arrived past which the connection
+    /// is given up, see `StreamIds`
+    pub max_abandoned_streams: usize
+}
+
+impl Default for ConnectOptions {
+    fn default() -> ConnectOptions {
+        ConnectOptions::new()
+    }
+}
+
+impl ConnectOptions {
+    pub fn new() -> ConnectOptions {
+        ConnectOptions { compression: vec![], connect_timeout: None, request_timeout: None,
+                         max_abandoned_streams: DEFAULT_MAX_ABANDONED_STREAMS }
     }
 }
 
 pub fn connect(ip: &'static str, port: u16, creds:Option<&Vec<CowStr>>) -> RCResult<Client> {
+    connect_with_compression(ip, port, creds, &[])
+}
+
+///
+/// Connects using the first algorithm in `compression` that the server supports, or no compression at all
+/// if there is none.
+///
+pub fn connect_with_compression(ip: &'static str, port: u16, creds:Option<&Vec<CowStr>>, compression: &[Compression]) -> RCResult<Client> {
+    let mut options = ConnectOptions::new();
+    options.compression = compression.to_vec();
+    connect_with_options(ip, port, creds, &options)
+}
+
+fn open_socket(ip: &str, port: u16, timeout: Option<Duration>) -> RCResult<TcpStream> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return match TcpStream::connect((ip, port)) {
+            Ok(socket) => Ok(socket),
+            Err(_) => Err(RCError::new(format!("Failed to connect to server at {}:{}", ip, port), ConnectionError))
+        }
+    };
+    let addrs = try_io!((ip, port).to_socket_addrs(), "Error resolving server address");
+    let mut kind = ConnectionError;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(socket) => return Ok(socket),
+            Err(ref err) if is_timeout(err) => kind = TimeoutError,
+            Err(_) => ()
+        }
+    }
+    Err(RCError::new(format!("Failed to connect to server at {}:{}", ip, port), kind))
+}
+
+// Blocking connection for the handshake, a new socket is opened for each protocol version tried
+struct BlockingTransport<'o> {
+    ip: &'static str,
+    port: u16,
+    options: &'o ConnectOptions,
+    client: Option<Client>
+}
 
-    let mut version = CQL_MAX_SUPPORTED_VERSION;
+impl<'o> BlockingTransport<'o> {
+    fn client(&mut self) -> RCResult<&mut Client> {
+        match self.client {
+            Some(ref mut client) => Ok(client),
+            None => Err(RCError::new("Not connected", ConnectionError))
+        }
+    }
+}
+
+impl<'o> Transport for BlockingTransport<'o> {
+    async fn reconnect(&mut self, version: u8) -> RCResult<()> {
+        let socket = try_rc!(open_socket(self.ip, self.port, self.options.connect_timeout), "Error connecting");
+        let mut client = Client::new(socket, version);
+        client.request_timeout = self.options.request_timeout;
+        client.streams = StreamIds::new(self.options.max_abandoned_streams);
+        self.client = Some(client);
+        Ok(())
+    }
+
+    async fn roundtrip(&mut self, request: &mut CqlRequest<'_>) -> RCResult<CqlResponse> {
+        let client = try_rc!(self.client(), "Error sending request");
+        let stream = try_rc!(client.send_request(request), "Error sending request");
+        client.read_response(stream)
+    }
 
-    while version >= 0x01 {
-        let res = std::net::TcpStream::connect((ip, port));
-        if res.is_err() {
-            return Err(RCError::new(format!("Failed to connect to server at {}:{}", ip, port), ConnectionError));
+    fn expect_compression(&mut self, compression: Compression) {
+        if let Some(ref mut client) = self.client {
+            client.frames.set_compression(compression);
         }
-        
-        let mut socket = res.unwrap();
+    }
 
-        match send_startup(& mut socket, version, creds) {
-            Ok(_) => return Ok(Client::new(socket, version)),
-            Err(e) => println!("Error connecting with protocol version v{}: {}", version, e.desc)
+    fn start_framing(&mut self, compression: Compression) {
+        if let Some(ref mut client) = self.client {
+            client.compression = compression;
+            if client.version >= 5 {
+                client.frames.enable_segments(compression);
+            }
         }
-        version -= 1;
     }
-    Err(RCError::new("Unable to find suitable protocol version (v1, v2, v3)", ReadError))
+}
+
+///
+/// Connects with the given `options`. A connection that times out fails with a `TimeoutError`.
+///
+pub fn connect_with_options(ip: &'static str, port: u16, creds:Option<&Vec<CowStr>>, options: &ConnectOptions) -> RCResult<Client> {
+    let mut transport = BlockingTransport { ip: ip, port: port, options: options, client: None };
+    let supported = try_rc!(block_on(handshake(&mut transport, creds, &options.compression)), "Error connecting");
+    let mut client = match transport.client {
+        Some(client) => client,
+        None => return Err(RCError::new("Not connected", ConnectionError))
+    };
+    client.supported = supported;
+    Ok(client)
 }
@@ -0,0 +1,62 @@
+extern crate std;
+extern crate byteorder;
+extern crate lz4_flex;
+extern crate snap;
+
+use self::byteorder::{ReadBytesExt, WriteBytesExt, BigEndian};
+
+use super::def::*;
+use super::def::RCErrorType::*;
+
+// Frame bodies are limited to 256MB by the protocol
+pub const MAX_BODY_LENGTH: u32 = 256 * 1024 * 1024;
+
+///
+/// Compresses a frame body. LZ4 bodies are prefixed with the uncompressed length as a big endian [int].
+///
+pub fn compress(data: &[u8], compression: Compression) -> RCResult<Vec<u8>> {
+    match compression {
+        Compression::Lz4 => {
+            let mut buf: Vec<u8> = Vec::with_capacity(4 + data.len());
+            try_bo!(buf.write_u32::<BigEndian>(data.len() as u32), "Error compressing body (lz4 length)");
+            buf.extend(lz4_flex::block::compress(data));
+            Ok(buf)
+        },
+        Compression::Snappy => {
+            match snap::raw::Encoder::new().compress_vec(data) {
+                Ok(buf) => Ok(buf),
+                Err(err) => Err(RCError::new(format!("Error compressing body (snappy) -> {}", err), WriteError))
+            }
+        },
+        Compression::None => Ok(data.to_vec())
+    }
+}
+
+///
+/// Decompresses a frame body. The uncompressed length of LZ4 bodies is checked before allocating it,
+/// as a corrupt prefix could otherwise claim gigabytes.
+///
+pub fn decompress(data: &[u8], compression: Compression) -> RCResult<Vec<u8>> {
+    match compression {
+        Compression::Lz4 => {
+            let mut reader = data;
+            let len = try_bo!(reader.read_u32::<BigEndian>(), "Error decompressing body (lz4 length)");
+            if len > MAX_BODY_LENGTH {
+                return Err(RCError::new(format!("Error decompressing body (lz4) -> invalid length {}", len), ReadError))
+            }
+            match lz4_flex::block::decompress(reader, len as usize) {
+                Ok(ref body) if body.len() != len as usize =>
+                    Err(RCError::new(format!("Error decompressing body (lz4) -> expected {} bytes, got {}", len, body.len()), ReadError)),
+                Ok(body) => Ok(body),
+                Err(err) => Err(RCError::new(format!("Error decompressing body (lz4) -> {}", err), ReadError))
+            }
+        },
+        Compression::Snappy => {
+            match snap::raw::Decoder::new().decompress_vec(data) {
+                Ok(body) => Ok(body),
+                Err(err) => Err(RCError::new(format!("Error decompressing body (snappy) -> {}", err), ReadError))
+            }
+        },
+        Compression::None => Err(RCError::new("Received a compressed frame but no compression was negotiated", ReadError))
+    }
+}
@@ -0,0 +1,89 @@
+extern crate std;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+
+use std::time::Duration;
+
+#[cfg(feature = "chrono")]
+use self::chrono::{Datelike, NaiveDate, NaiveTime, Timelike};
+
+// `date` values count days from 2^31, which is the epoch (1970-01-01)
+const DATE_EPOCH: i64 = 1 << 31;
+const NANOS_PER_DAY: i64 = 86_400_000_000_000;
+// Days from 0001-01-01 (day 1 of the common era) to the epoch
+#[cfg(feature = "chrono")]
+const EPOCH_DAYS_FROM_CE: i64 = 719_163;
+
+///
+/// Days since the epoch of a CQL `date`, negative for dates before it.
+///
+pub fn cql_date_to_days(date: u32) -> i64 {
+    date as i64 - DATE_EPOCH
+}
+
+///
+/// CQL `date` of the day `days` after the epoch, None if it is out of the range of the type.
+///
+pub fn days_to_cql_date(days: i64) -> Option<u32> {
+    let date = days + DATE_EPOCH;
+    if date < 0 || date > u32::MAX as i64 {
+        None
+    } else {
+        Some(date as u32)
+    }
+}
+
+///
+/// Time since midnight of a CQL `time` (nanoseconds), None if it is not within a day.
+///
+pub fn cql_time_to_duration(time: i64) -> Option<Duration> {
+    if !(0..NANOS_PER_DAY).contains(&time) {
+        None
+    } else {
+        Some(Duration::new((time / 1_000_000_000) as u64, (time % 1_000_000_000) as u32))
+    }
+}
+
+///
+/// CQL `time` of `since_midnight`, None if it is not within a day.
+///
+pub fn duration_to_cql_time(since_midnight: Duration) -> Option<i64> {
+    let secs = since_midnight.as_secs();
+    if secs >= 86_400 {
+        return None
+    }
+    Some(secs as i64 * 1_000_000_000 + since_midnight.subsec_nanos() as i64)
+}
+
+#[cfg(feature = "chrono")]
+pub fn cql_date_to_naive_date(date: u32) -> Option<NaiveDate> {
+    let days_from_ce = cql_date_to_days(date) + EPOCH_DAYS_FROM_CE;
+    if days_from_ce < i32::MIN as i64 || days_from_ce > i32::MAX as i64 {
+        return None
+    }
+    NaiveDate::from_num_days_from_ce_opt(days_from_ce as i32)
+}
+
+///
+/// CQL `date` of `date`. Every `NaiveDate` is within the range of the type.
+///
+#[cfg(feature = "chrono")]
+pub fn naive_date_to_cql_date(date: NaiveDate) -> u32 {
+    (date.num_days_from_ce() as i64 - EPOCH_DAYS_FROM_CE + DATE_EPOCH) as u32
+}
+
+#[cfg(feature = "chrono")]
+pub fn cql_time_to_naive_time(time: i64) -> Option<NaiveTime> {
+    if !(0..NANOS_PER_DAY).contains(&time) {
+        return None
+    }
+    NaiveTime::from_num_seconds_from_midnight_opt((time / 1_000_000_000) as u32, (time % 1_000_000_000) as u32)
+}
+
+///
+/// CQL `time` of `time`. A leap second is folded into the last second of the day.
+///
+#[cfg(feature = "chrono")]
+pub fn naive_time_to_cql_time(time: NaiveTime) -> i64 {
+    time.num_seconds_from_midnight() as i64 * 1_000_000_000 + std::cmp::min(time.nanosecond(), 999_999_999) as i64
+}
@@ -6,8 +6,8 @@ use std::net::Ipv4Addr;
 use std::net::Ipv6Addr;
 use self::uuid::Uuid;
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::ops::Deref;
-use std::error::Error;
 
 pub type CowStr = Cow<'static, str>;
 
@@ -73,7 +73,7 @@ pub fn opcode_response(val: u8) -> OpcodeResponse {
 }
 
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Consistency {
     Any = 0x0000,
     One = 0x0001,
@@ -83,9 +83,46 @@ pub enum Consistency {
     All = 0x0005,
     LocalQuorum = 0x0006,
     EachQuorum = 0x0007,
+    Serial = 0x0008,
+    LocalSerial = 0x0009,
+    LocalOne = 0x000A,
     Unknown,
 }
 
+pub fn cql_consistency(val: u16) -> Consistency {
+    match val {
+        0x0000 => Consistency::Any,
+        0x0001 => Consistency::One,
+        0x0002 => Consistency::Two,
+        0x0003 => Consistency::Three,
+        0x0004 => Consistency::Quorum,
+        0x0005 => Consistency::All,
+        0x0006 => Consistency::LocalQuorum,
+        0x0007 => Consistency::EachQuorum,
+        0x0008 => Consistency::Serial,
+        0x0009 => Consistency::LocalSerial,
+        0x000A => Consistency::LocalOne,
+        _ => Consistency::Unknown
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    None,
+    Lz4,
+    Snappy
+}
+
+impl Compression {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Compression::None => "none",
+            Compression::Lz4 => "lz4",
+            Compression::Snappy => "snappy"
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub enum BatchType {
     Logged = 0x00,
@@ -93,7 +130,7 @@ pub enum BatchType {
     Counter = 0x02
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum CqlValueType {
     ColumnCustom = 0x0000,
     ColumnASCII = 0x0001,
@@ -112,9 +149,16 @@ pub enum CqlValueType {
     ColumnVarint = 0x000E,
     ColumnTimeUuid = 0x000F,
     ColumnInet = 0x0010,
+    ColumnDate = 0x0011,
+    ColumnTime = 0x0012,
+    ColumnSmallInt = 0x0013,
+    ColumnTinyInt = 0x0014,
+    ColumnDuration = 0x0015,
     ColumnList = 0x0020,
     ColumnMap = 0x0021,
     ColumnSet = 0x0022,
+    ColumnUdt = 0x0030,
+    ColumnTuple = 0x0031,
     ColumnUnknown,
 }
 
@@ -137,15 +181,22 @@ pub fn cql_column_type(val: u16) -> CqlValueType {
         0x000E => CqlValueType::ColumnVarint,
         0x000F => CqlValueType::ColumnTimeUuid,
         0x0010 => CqlValueType::ColumnInet,
+        0x0011 => CqlValueType::ColumnDate,
+        0x0012 => CqlValueType::ColumnTime,
+        0x0013 => CqlValueType::ColumnSmallInt,
+        0x0014 => CqlValueType::ColumnTinyInt,
+        0x0015 => CqlValueType::ColumnDuration,
         0x0020 => CqlValueType::ColumnList,
         0x0021 => CqlValueType::ColumnMap,
         0x0022 => CqlValueType::ColumnSet,
+        0x0030 => CqlValueType::ColumnUdt,
+        0x0031 => CqlValueType::ColumnTuple,
         _ => CqlValueType::ColumnUnknown
     }
 }
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum RCErrorType {
     ReadError,
     WriteError,
@@ -153,6 +204,8 @@ pub enum RCErrorType {
     ConnectionError,
     NoDataError,
     GenericError,
+    ChecksumError,
+    TimeoutError,
     IOError
 }
 
@@ -182,10 +235,6 @@ impl std::error::Error for RCError {
     fn description(&self) -> &str {
         return self.desc.deref();
     }
-
-    fn cause(&self) -> Option<&std::error::Error> {
-        return None;
-    }
 }
 
 impl std::fmt::Display for RCError {
@@ -206,9 +255,12 @@ pub struct CqlStringMap {
     pub pairs: Vec<CqlPair>,
 }
 
+pub type CqlStringMultiMap = BTreeMap<CowStr, Vec<CowStr>>;
+pub type CqlBytesMap = BTreeMap<CowStr, Vec<u8>>;
+
 pub struct CqlPair {
-    pub key: &'static str,
-    pub value: &'static str,
+    pub key: CowStr,
+    pub value: CowStr,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -217,27 +269,65 @@ pub enum CqlBytesSize {
    Cqli16 
 }
 
-pub struct CqlTableDesc {
-    pub keyspace: String,
-    pub tablename: String
+///
+/// Complete description of a type, including the types it is made of. `CqlColMetadata::col_type`
+/// and its aux types only tell the outermost ones.
+///
+#[derive(Debug, Clone)]
+pub enum CqlType {
+    Native(CqlValueType),
+    Custom(CowStr),
+    List(Box<CqlType>),
+    Map(Box<CqlType>, Box<CqlType>),
+    Set(Box<CqlType>),
+    Udt(CqlUdtType),
+    Tuple(Vec<CqlType>)
+}
+
+impl CqlType {
+    pub fn value_type(&self) -> CqlValueType {
+        match *self {
+            CqlType::Native(ref value_type) => value_type.clone(),
+            CqlType::Custom(_) => CqlValueType::ColumnCustom,
+            CqlType::List(_) => CqlValueType::ColumnList,
+            CqlType::Map(_, _) => CqlValueType::ColumnMap,
+            CqlType::Set(_) => CqlValueType::ColumnSet,
+            CqlType::Udt(_) => CqlValueType::ColumnUdt,
+            CqlType::Tuple(_) => CqlValueType::ColumnTuple
+        }
+    }
+}
+
+///
+/// User defined type, with its fields in the order of the definition.
+///
+#[derive(Debug, Clone)]
+pub struct CqlUdtType {
+    pub keyspace: CowStr,
+    pub name: CowStr,
+    pub fields: Vec<(CowStr, CqlType)>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CqlColMetadata {
     pub keyspace: CowStr,
     pub table: CowStr,
     pub col_name: CowStr,
     pub col_type: CqlValueType,
     pub col_type_aux1: CqlValueType,
-    pub col_type_aux2: CqlValueType
+    pub col_type_aux2: CqlValueType,
+    pub col_type_info: CqlType
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CqlMetadata {
     pub flags: u32,
     pub column_count: u32,
     pub keyspace: CowStr,
     pub table: CowStr,
+    pub pk_indices: Vec<u16>,
+    pub paging_state: Option<Vec<u8>>,
+    pub new_metadata_id: Option<Vec<u8>>,
     pub row_metadata: Vec<CqlColMetadata>,
 }
 
@@ -247,9 +337,209 @@ pub struct Pair<T, V> {
     pub value: V
 }
 
+///
+/// Arbitrary precision decimal number, whose value is `unscaled * 10^-scale`.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decimal {
+    pub unscaled: num::BigInt,
+    pub scale: i32
+}
+
+impl Decimal {
+    pub fn new(unscaled: num::BigInt, scale: i32) -> Decimal {
+        Decimal { unscaled: unscaled, scale: scale }
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let unscaled = self.unscaled.to_string();
+        let (sign, digits) = match unscaled.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", &unscaled[..])
+        };
+        if self.scale <= 0 {
+            return write!(f, "{}{}{}", sign, digits, "0".repeat(-(self.scale as i64) as usize))
+        }
+        let scale = self.scale as usize;
+        if digits.len() > scale {
+            write!(f, "{}{}.{}", sign, &digits[.. digits.len() - scale], &digits[digits.len() - scale ..])
+        } else {
+            write!(f, "{}0.{}{}", sign, "0".repeat(scale - digits.len()), digits)
+        }
+    }
+}
+
+///
+/// Parses the plain notation of decimals, e.g. `-1234.50`, keeping all the fractional digits as scale.
+///
+impl std::str::FromStr for Decimal {
+    type Err = RCError;
+
+    fn from_str(s: &str) -> RCResult<Decimal> {
+        let (int_part, frac_part) = match s.find('.') {
+            Some(pos) => (&s[.. pos], &s[pos + 1 ..]),
+            None => (s, "")
+        };
+        if !frac_part.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(RCError::new(format!("Invalid decimal: {}", s), RCErrorType::GenericError))
+        }
+        match format!("{}{}", int_part, frac_part).parse::<num::BigInt>() {
+            Ok(unscaled) => Ok(Decimal::new(unscaled, frac_part.len() as i32)),
+            Err(_) => Err(RCError::new(format!("Invalid decimal: {}", s), RCErrorType::GenericError))
+        }
+    }
+}
+
+///
+/// Value of the `duration` type. Months and days cannot be converted to a fixed amount of time, so
+/// they are kept apart. All three components have the same sign.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Duration {
+    pub months: i32,
+    pub days: i32,
+    pub nanoseconds: i64
+}
+
+impl Duration {
+    pub fn new(months: i32, days: i32, nanoseconds: i64) -> Duration {
+        Duration { months: months, days: days, nanoseconds: nanoseconds }
+    }
+
+    ///
+    /// Whether all the components have the same sign. Durations with mixed signs cannot be serialized.
+    ///
+    pub fn is_valid(&self) -> bool {
+        (self.months >= 0 && self.days >= 0 && self.nanoseconds >= 0) ||
+        (self.months <= 0 && self.days <= 0 && self.nanoseconds <= 0)
+    }
+}
+
+// Units of the textual forms, in the order they must appear, with the (months, days, nanoseconds) of each
+const DURATION_UNITS: [(&str, i64, i64, i64); 10] = [
+    ("y", 12, 0, 0), ("mo", 1, 0, 0), ("w", 0, 7, 0), ("d", 0, 1, 0),
+    ("h", 0, 0, 3_600_000_000_000), ("m", 0, 0, 60_000_000_000), ("s", 0, 0, 1_000_000_000),
+    ("ms", 0, 0, 1_000_000), ("us", 0, 0, 1_000), ("ns", 0, 0, 1)];
+const ISO_DATE_UNITS: [(&str, i64, i64, i64); 4] = [("y", 12, 0, 0), ("m", 1, 0, 0), ("w", 0, 7, 0), ("d", 0, 1, 0)];
+const ISO_TIME_UNITS: [(&str, i64, i64, i64); 3] = [("h", 0, 0, 3_600_000_000_000), ("m", 0, 0, 60_000_000_000), ("s", 0, 0, 1_000_000_000)];
+
+// Adds up a sequence of quantities with units, e.g. `1h30m`
+fn parse_duration_units(s: &str, units: &[(&'static str, i64, i64, i64)], total: &mut [i64; 3]) -> Option<()> {
+    let mut rest = s;
+    let mut last_unit = None;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let quantity: i64 = rest[.. digits].parse().ok()?;
+        rest = &rest[digits ..];
+        let unit_len = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        let unit = rest[.. unit_len].to_lowercase().replace("µ", "u");
+        rest = &rest[unit_len ..];
+        let pos = units.iter().position(|&(symbol, _, _, _)| symbol == unit)?;
+        if last_unit.is_some_and(|last| pos <= last) {
+            return None
+        }
+        last_unit = Some(pos);
+        let (_, months, days, nanoseconds) = units[pos];
+        total[0] = total[0].checked_add(quantity.checked_mul(months)?)?;
+        total[1] = total[1].checked_add(quantity.checked_mul(days)?)?;
+        total[2] = total[2].checked_add(quantity.checked_mul(nanoseconds)?)?;
+    }
+    Some(())
+}
+
+// ISO 8601 durations without the leading `P`: `1Y2M3DT4H5M6S`, `2W` or `0001-02-03T04:05:06`
+fn parse_iso_duration(s: &str, total: &mut [i64; 3]) -> Option<()> {
+    let (date, time) = match s.find('T') {
+        Some(pos) => (&s[.. pos], Some(&s[pos + 1 ..])),
+        None => (s, None)
+    };
+    if date.contains('-') {
+        let date: Vec<i64> = date.split('-').map(|n| n.parse().ok()).collect::<Option<Vec<i64>>>()?;
+        let time: Vec<i64> = time?.split(':').map(|n| n.parse().ok()).collect::<Option<Vec<i64>>>()?;
+        if date.len() != 3 || time.len() != 3 {
+            return None
+        }
+        let text = format!("{}y{}mo{}d{}h{}m{}s", date[0], date[1], date[2], time[0], time[1], time[2]);
+        return parse_duration_units(&text, &DURATION_UNITS, total)
+    }
+    if date.is_empty() && time.is_none_or(|time| time.is_empty()) {
+        return None
+    }
+    parse_duration_units(date, &ISO_DATE_UNITS, total)?;
+    match time {
+        Some("") => None,
+        Some(time) => parse_duration_units(time, &ISO_TIME_UNITS, total),
+        None => Some(())
+    }
+}
+
+///
+/// Formats the duration like Cassandra does, e.g. `-1y2mo3d4h5m6s7ms8us9ns`, which can be used
+/// as a duration literal in queries.
+///
+impl std::fmt::Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        if self.months == 0 && self.days == 0 && self.nanoseconds == 0 {
+            return write!(f, "0s")
+        }
+        if self.months < 0 || self.days < 0 || self.nanoseconds < 0 {
+            write!(f, "-")?;
+        }
+        let months = (self.months as i64).unsigned_abs();
+        let days = (self.days as i64).unsigned_abs();
+        let nanoseconds = self.nanoseconds.unsigned_abs();
+        let parts = [(months / 12, "y"), (months % 12, "mo"), (days, "d"),
+                     (nanoseconds / 3_600_000_000_000, "h"), (nanoseconds / 60_000_000_000 % 60, "m"),
+                     (nanoseconds / 1_000_000_000 % 60, "s"), (nanoseconds / 1_000_000 % 1000, "ms"),
+                     (nanoseconds / 1000 % 1000, "us"), (nanoseconds % 1000, "ns")];
+        for &(quantity, unit) in parts.iter() {
+            if quantity != 0 {
+                write!(f, "{}{}", quantity, unit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+///
+/// Parses the formats accepted by Cassandra: quantities with units (`1h30m`, `2w`, `-3d12h`) and
+/// ISO 8601 (`P1Y2M3DT4H5M6S`, `P2W`, `P0001-02-03T04:05:06`).
+///
+impl std::str::FromStr for Duration {
+    type Err = RCError;
+
+    fn from_str(s: &str) -> RCResult<Duration> {
+        let (negative, body) = match s.strip_prefix('-') {
+            Some(body) => (true, body),
+            None => (false, s)
+        };
+        let mut total = [0i64; 3];
+        let parsed = if let Some(iso) = body.strip_prefix('P') {
+            parse_iso_duration(iso, &mut total)
+        } else if body.is_empty() {
+            None
+        } else {
+            parse_duration_units(body, &DURATION_UNITS, &mut total)
+        };
+        let sign = if negative { -1 } else { 1 };
+        match parsed {
+            Some(_) if total[0] <= i32::MAX as i64 && total[1] <= i32::MAX as i64 =>
+                Ok(Duration::new((sign * total[0]) as i32, (sign * total[1]) as i32, sign * total[2])),
+            _ => Err(RCError::new(format!("Invalid duration: {}", s), RCErrorType::GenericError))
+        }
+    }
+}
+
 pub type CQLList = Vec<CqlValue>;
 pub type CQLMap = Vec<Pair<CqlValue, CqlValue>>;
 pub type CQLSet = Vec<CqlValue>;
+/// Fields of a UDT value. They are bound by position: the names are not sent, so list the fields in the order
+/// of the type definition. Trailing ones can be left out
+pub type CQLUdt = Vec<(CowStr, CqlValue)>;
+/// Components of a tuple value, in order. Null components are `None` values of any type
+pub type CQLTuple = Vec<CqlValue>;
 
 #[derive(Debug)]
 pub enum CqlValue {
@@ -258,20 +548,28 @@ pub enum CqlValue {
     CqlBlob(Option<Vec<u8>>),
     CqlBoolean(Option<bool>),
     CqlCounter(Option<i64>),
-    CqlDecimal(Option<num::BigInt>),
+    CqlDate(Option<u32>),
+    CqlDecimal(Option<Decimal>),
     CqlDouble(Option<f64>),
+    CqlDuration(Option<Duration>),
     CqlFloat(Option<f32>),
     CqlInet(Option<IpAddr>),
     CqlInt(Option<i32>),
     CqlList(Option<CQLList>),
     CqlMap(Option<CQLMap>),
     CqlSet(Option<CQLSet>),
+    CqlSmallInt(Option<i16>),
     CqlText(Option<CowStr>),
+    CqlTime(Option<i64>),
     CqlTimestamp(Option<u64>),
+    CqlTinyInt(Option<i8>),
+    CqlTuple(Option<CQLTuple>),
+    CqlUdt(Option<CQLUdt>),
     CqlUuid(Option<Uuid>),
     CqlTimeUuid(Option<Uuid>),
     CqlVarchar(Option<CowStr>),
     CqlVarint(Option<num::BigInt>),
+    CqlUnset,
     CqlUnknown,
 }
 
@@ -286,61 +584,422 @@ pub struct CqlRows {
     pub rows: Vec<CqlRow>,
 }
 
+impl CqlRows {
+    ///
+    /// Whether the query has more rows, to be fetched with the paging state in the metadata.
+    ///
+    pub fn has_more_pages(&self) -> bool {
+        self.metadata.flags & 0x0002 != 0 && self.metadata.paging_state.is_some()
+    }
+}
+
 pub struct CqlRequest<'a> {
-    pub version: u8,
     pub flags: u8,
     pub stream: i16,
     pub opcode: OpcodeRequest,
     pub body: CqlRequestBody<'a>,
 }
 
+///
+/// Parameters of QUERY and EXECUTE requests. The query flags are derived from the parameters that are set.
+///
+#[derive(Clone)]
+pub struct QueryParams<'a> {
+    pub consistency: Consistency,
+    pub values: &'a [CqlValue],
+    pub named_values: &'a [(&'a str, CqlValue)],
+    pub skip_metadata: bool,
+    pub page_size: Option<i32>,
+    pub paging_state: Option<Vec<u8>>,
+    pub serial_consistency: Option<Consistency>,
+    pub timestamp: Option<i64>,
+    pub keyspace: Option<CowStr>
+}
+
+impl<'a> QueryParams<'a> {
+    pub fn new(consistency: Consistency, values: &'a [CqlValue]) -> QueryParams<'a> {
+        QueryParams {
+            consistency: consistency,
+            values: values,
+            named_values: &[],
+            skip_metadata: false,
+            page_size: None,
+            paging_state: None,
+            serial_consistency: None,
+            timestamp: None,
+            keyspace: None
+        }
+    }
+
+    pub fn flags(&self) -> u8 {
+        let mut flags = 0x00;
+        if !self.values.is_empty() {
+            flags |= 0x01;
+        }
+        if !self.named_values.is_empty() {
+            flags |= 0x01 | 0x40;
+        }
+        if self.skip_metadata {
+            flags |= 0x02;
+        }
+        if self.page_size.is_some() {
+            flags |= 0x04;
+        }
+        if self.paging_state.is_some() {
+            flags |= 0x08;
+        }
+        if self.serial_consistency.is_some() {
+            flags |= 0x10;
+        }
+        if self.timestamp.is_some() {
+            flags |= 0x20;
+        }
+        if self.keyspace.is_some() {
+            flags |= 0x80;
+        }
+        flags
+    }
+}
+
+///
+/// Parameters of BATCH requests. Flags were introduced in protocol v3. The names for values flag is
+/// not supported: the protocol spec states that it does not work for batches (CASSANDRA-10246), so
+/// values of batched queries are always bound by position.
+///
+#[derive(Clone)]
+pub struct BatchParams {
+    pub consistency: Consistency,
+    pub serial_consistency: Option<Consistency>,
+    pub timestamp: Option<i64>,
+    pub keyspace: Option<CowStr>
+}
+
+impl BatchParams {
+    pub fn new(consistency: Consistency) -> BatchParams {
+        BatchParams {
+            consistency: consistency,
+            serial_consistency: None,
+            timestamp: None,
+            keyspace: None
+        }
+    }
+
+    pub fn flags(&self) -> u8 {
+        let mut flags = 0x00;
+        if self.serial_consistency.is_some() {
+            flags |= 0x10;
+        }
+        if self.timestamp.is_some() {
+            flags |= 0x20;
+        }
+        if self.keyspace.is_some() {
+            flags |= 0x80;
+        }
+        flags
+    }
+}
+
+///
+/// Options of a single execution, accepted by every execution method of the clients. A `Consistency`
+/// converts into options that leave everything else to the defaults of the client.
+///
+#[derive(Clone, Debug)]
+pub struct QueryOptions {
+    pub consistency: Consistency,
+    pub serial_consistency: Option<Consistency>,
+    pub page_size: Option<i32>,
+    pub paging_state: Option<Vec<u8>>,
+    /// Microseconds since the epoch, generated by the client if None
+    pub timestamp: Option<i64>,
+    pub tracing: bool,
+    /// Whether the statement can be applied more than once with the same result. Not sent to the server, the
+    /// clients send such statements once more when their response times out
+    pub idempotent: bool,
+    /// Overrides the request timeout of the client
+    pub timeout: Option<std::time::Duration>,
+    /// Keyspace of the unqualified tables of a query or batch (protocol v5 or later)
+    pub keyspace: Option<CowStr>
+}
+
+impl QueryOptions {
+    pub fn new(consistency: Consistency) -> QueryOptions {
+        QueryOptions {
+            consistency: consistency,
+            serial_consistency: None,
+            page_size: None,
+            paging_state: None,
+            timestamp: None,
+            tracing: false,
+            idempotent: false,
+            timeout: None,
+            keyspace: None
+        }
+    }
+
+    pub fn query_params<'a>(&self, values: &'a [CqlValue]) -> QueryParams<'a> {
+        let mut params = QueryParams::new(self.consistency, values);
+        params.page_size = self.page_size;
+        params.paging_state = self.paging_state.clone();
+        params.serial_consistency = self.serial_consistency;
+        params.timestamp = self.timestamp;
+        params.keyspace = self.keyspace.clone();
+        params
+    }
+
+    pub fn batch_params(&self) -> BatchParams {
+        let mut params = BatchParams::new(self.consistency);
+        params.serial_consistency = self.serial_consistency;
+        params.timestamp = self.timestamp;
+        params.keyspace = self.keyspace.clone();
+        params
+    }
+}
+
+impl From<Consistency> for QueryOptions {
+    fn from(consistency: Consistency) -> QueryOptions {
+        QueryOptions::new(consistency)
+    }
+}
+
 pub enum CqlRequestBody<'a> {
     RequestStartup(CqlStringMap),
-    RequestQuery(&'a str, Consistency, u8),
+    RequestQuery(&'a str, QueryParams<'a>),
     RequestPrepare(&'a str),
-    RequestExec(Vec<u8>, &'a [CqlValue], Consistency, u8),
-    RequestBatch(Vec<Query>, BatchType, Consistency, u8),
+    RequestExec(&'a CqlPreparedStat, QueryParams<'a>),
+    RequestBatch(Vec<Query>, BatchType, BatchParams),
     RequestOptions,
+    RequestRegister(&'a [CqlEventType]),
     RequestAuthResponse(Vec<u8>),
 }
 
+///
+/// Error returned by the server, with the additional information of each kind of error.
+/// Read/write failures carry the failure reason of each replica from protocol v5 on.
+///
+#[derive(Debug)]
+pub enum ServerError {
+    ServerFailure,
+    ProtocolError,
+    BadCredentials,
+    Unavailable { consistency: Consistency, required: i32, alive: i32 },
+    Overloaded,
+    IsBootstrapping,
+    TruncateError,
+    WriteTimeout { consistency: Consistency, received: i32, block_for: i32, write_type: CowStr, contentions: Option<u16> },
+    ReadTimeout { consistency: Consistency, received: i32, block_for: i32, data_present: bool },
+    ReadFailure { consistency: Consistency, received: i32, block_for: i32, num_failures: i32,
+                  reasons: Vec<(IpAddr, u16)>, data_present: bool },
+    FunctionFailure { keyspace: CowStr, function: CowStr, arg_types: Vec<CowStr> },
+    WriteFailure { consistency: Consistency, received: i32, block_for: i32, num_failures: i32,
+                   reasons: Vec<(IpAddr, u16)>, write_type: CowStr },
+    CdcWriteFailure,
+    CasWriteUnknown { consistency: Consistency, received: i32, block_for: i32 },
+    SyntaxError,
+    Unauthorized,
+    Invalid,
+    ConfigError,
+    AlreadyExists { keyspace: CowStr, table: CowStr },
+    Unprepared { id: Vec<u8> },
+    Unknown(u32)
+}
+
+impl ServerError {
+    pub fn code(&self) -> u32 {
+        match *self {
+            ServerError::ServerFailure => 0x0000,
+            ServerError::ProtocolError => 0x000A,
+            ServerError::BadCredentials => 0x0100,
+            ServerError::Unavailable { .. } => 0x1000,
+            ServerError::Overloaded => 0x1001,
+            ServerError::IsBootstrapping => 0x1002,
+            ServerError::TruncateError => 0x1003,
+            ServerError::WriteTimeout { .. } => 0x1100,
+            ServerError::ReadTimeout { .. } => 0x1200,
+            ServerError::ReadFailure { .. } => 0x1300,
+            ServerError::FunctionFailure { .. } => 0x1400,
+            ServerError::WriteFailure { .. } => 0x1500,
+            ServerError::CdcWriteFailure => 0x1600,
+            ServerError::CasWriteUnknown { .. } => 0x1700,
+            ServerError::SyntaxError => 0x2000,
+            ServerError::Unauthorized => 0x2100,
+            ServerError::Invalid => 0x2200,
+            ServerError::ConfigError => 0x2300,
+            ServerError::AlreadyExists { .. } => 0x2400,
+            ServerError::Unprepared { .. } => 0x2500,
+            ServerError::Unknown(code) => code
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct CqlResponse {
     pub version: u8,
     pub flags: u8,
     pub stream: i16,
     pub opcode: OpcodeResponse,
+    pub tracing_id: Option<Uuid>,
+    pub warnings: Vec<CowStr>,
+    pub custom_payload: CqlBytesMap,
     pub body: CqlResponseBody,
 }
 
+///
+/// Outcome of a conditional update (lightweight transaction). When the update is not applied the
+/// server returns the current values of the columns involved in the condition.
+///
+#[derive(Debug)]
+pub struct LwtResult {
+    pub applied: bool,
+    pub existing: Vec<(CowStr, CqlValue)>
+}
+
+impl CqlResponse {
+    pub fn lwt_result(self) -> RCResult<LwtResult> {
+        let rows = match self.body {
+            CqlResponseBody::ResultRows(rows) => rows,
+            CqlResponseBody::ResponseError(_, msg) => return Err(RCError::new(format!("Conditional update failed: {}", msg), RCErrorType::ReadError)),
+            _ => return Err(RCError::new("Response to a conditional update does not contain rows", RCErrorType::ReadError))
+        };
+        let names: Vec<CowStr> = rows.metadata.row_metadata.into_iter().map(|meta| meta.col_name).collect();
+        let mut cols = match rows.rows.into_iter().next() {
+            Some(row) => row.cols.into_iter(),
+            None => return Err(RCError::new("Response to a conditional update has no rows", RCErrorType::ReadError))
+        };
+        let applied = match cols.next() {
+            Some(CqlValue::CqlBoolean(Some(applied))) => applied,
+            _ => return Err(RCError::new("Response to a conditional update has no [applied] column", RCErrorType::ReadError))
+        };
+        Ok(LwtResult {
+            applied: applied,
+            existing: names.into_iter().skip(1).zip(cols).collect()
+        })
+    }
+}
+
 #[derive(Debug)]
 pub enum CqlResponseBody {
-    ResponseError(u32, CowStr),
+    ResponseError(ServerError, CowStr),
     ResponseReady,
     ResponseAuthenticate(CowStr),
     ResponseAuthChallenge(Vec<u8>),
     ResponseAuthSuccess(Vec<u8>),
+    ResponseSupported(CqlStringMultiMap),
+    ResponseEvent(CqlEvent),
 
     ResultVoid,
     ResultRows(CqlRows),
     ResultKeyspace(CowStr),
     ResultPrepared(CqlPreparedStat),
-    ResultSchemaChange(CowStr, CowStr, CowStr),
+    ResultSchemaChange(CqlSchemaChange),
     ResultUnknown,
 
     ResponseEmpty,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CqlEventType {
+    TopologyChange,
+    StatusChange,
+    SchemaChange
+}
+
+impl CqlEventType {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            CqlEventType::TopologyChange => "TOPOLOGY_CHANGE",
+            CqlEventType::StatusChange => "STATUS_CHANGE",
+            CqlEventType::SchemaChange => "SCHEMA_CHANGE"
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TopologyChangeType {
+    NewNode,
+    RemovedNode,
+    MovedNode
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StatusChangeType {
+    Up,
+    Down
+}
+
+///
+/// Schema change, either pushed as an event or returned as the result of a DDL query.
+/// `name` is empty for keyspace changes and `arg_types` is only set for functions and aggregates.
+///
+#[derive(Debug)]
+pub struct CqlSchemaChange {
+    pub change: CowStr,
+    pub target: CowStr,
+    pub keyspace: CowStr,
+    pub name: CowStr,
+    pub arg_types: Vec<CowStr>,
+}
+
+#[derive(Debug)]
+pub enum CqlEvent {
+    TopologyChange { change: TopologyChangeType, address: IpAddr, port: i32 },
+    StatusChange { change: StatusChangeType, address: IpAddr, port: i32 },
+    SchemaChange(CqlSchemaChange),
+}
+
+#[derive(Debug)]
+pub struct TraceEvent {
+    pub event_id: Uuid,
+    pub activity: CowStr,
+    pub source: IpAddr,
+    pub source_elapsed: i32,
+    pub thread: CowStr,
+}
+
+#[derive(Debug)]
+pub struct TraceSession {
+    pub session_id: Uuid,
+    pub coordinator: IpAddr,
+    pub duration: i32,
+    pub request: CowStr,
+    pub started_at: u64,
+    pub events: Vec<TraceEvent>,
+}
+
 #[derive(Debug)]
 pub struct CqlPreparedStat {
     pub id: Vec<u8>,
+    pub result_metadata_id: Option<Vec<u8>>,
     pub meta: CqlMetadata,
     pub meta_result: Option<CqlMetadata>
 }
 
+impl CqlPreparedStat {
+    ///
+    /// Takes the result metadata of an execution of this statement when the server reports that it changed
+    /// (protocol v5 or later), so that later executions with `skip_metadata` decode the new columns.
+    /// Returns whether the statement was updated.
+    ///
+    pub fn update_result_metadata(&mut self, rows: &CqlRows) -> bool {
+        let metadata = &rows.metadata;
+        match metadata.new_metadata_id {
+            Some(ref id) if metadata.flags & 0x0004 == 0 => {
+                self.result_metadata_id = Some(id.clone());
+                self.meta_result = Some(CqlMetadata {
+                    paging_state: None,
+                    new_metadata_id: None,
+                    ..metadata.clone()
+                });
+                true
+            },
+            _ => false
+        }
+    }
+}
+
 
 pub enum Query {
     QueryStr(CowStr),
+    QueryStrValues(CowStr, Vec<CqlValue>),
     QueryPrepared(Vec<u8>, Vec<CqlValue>),
     QueryBatch(Vec<Query>)
 }
@@ -348,3 +1007,4 @@ pub enum Query {
 
 
 
+
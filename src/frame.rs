@@ -0,0 +1,86 @@
+extern crate std;
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use super::def::*;
+use super::reader::*;
+use super::segment::SegmentDecoder;
+
+///
+/// Accumulates the bytes read from a connection until they make up whole frames, which are only then
+/// decoded. Reads can thus be interrupted (e.g. by a timeout) at any point without losing data.
+/// From protocol v5 on, the bytes are first decoded as segments.
+///
+pub struct FrameBuffer {
+    segments: Option<SegmentDecoder>,
+    compression: Compression,
+    buf: Vec<u8>
+}
+
+impl FrameBuffer {
+    pub fn new() -> FrameBuffer {
+        FrameBuffer { segments: None, compression: Compression::None, buf: vec![] }
+    }
+
+    ///
+    /// Decompresses the bodies of the frames flagged as compressed with `compression`. The server may compress
+    /// its response to STARTUP already, before the client compresses anything.
+    ///
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    pub fn enable_segments(&mut self, compression: Compression) {
+        self.segments = Some(SegmentDecoder::new(compression));
+    }
+
+    pub fn has_segments(&self) -> bool {
+        self.segments.is_some()
+    }
+
+    pub fn push(&mut self, data: &[u8]) -> RCResult<()> {
+        match self.segments {
+            Some(ref mut decoder) => {
+                decoder.push(data);
+                while let Some(segment) = try_rc!(decoder.next_segment(), "Error decoding segment") {
+                    self.buf.extend(segment.payload);
+                }
+            },
+            None => self.buf.extend(data)
+        }
+        Ok(())
+    }
+
+    fn frame_length(&self, version: u8) -> Option<usize> {
+        let header_len = if version >= 3 { 9 } else { 8 };
+        if self.buf.len() < header_len {
+            return None
+        }
+        let len = &self.buf[header_len - 4 .. header_len];
+        let body_len = ((len[0] as usize) << 24) | ((len[1] as usize) << 16) | ((len[2] as usize) << 8) | len[3] as usize;
+        if self.buf.len() < header_len + body_len {
+            None
+        } else {
+            Some(header_len + body_len)
+        }
+    }
+
+    ///
+    /// Decodes the next frame if it has been completely received. Rows of executions that skipped the
+    /// result metadata are decoded with the metadata cached for their stream in `result_meta`.
+    ///
+    pub fn next_frame(&mut self, version: u8, result_meta: &mut BTreeMap<i16, CqlMetadata>) -> RCResult<Option<CqlResponse>> {
+        let frame_len = match self.frame_length(version) {
+            Some(frame_len) => frame_len,
+            None => return Ok(None)
+        };
+        let frame: Vec<u8> = self.buf.drain(.. frame_len).collect();
+        // Segments are compressed as a whole, frames inside them never are
+        let compression = if self.segments.is_some() { Compression::None } else { self.compression };
+        let mut reader = Cursor::new(frame);
+        let header = try_rc!(reader.read_cql_frame_header(version), "Error reading CQL frame header");
+        let meta = result_meta.remove(&header.stream);
+        Ok(Some(try_rc!(reader.read_cql_response_body(header, version, compression, meta.as_ref()), "Error reading response")))
+    }
+}
@@ -0,0 +1,177 @@
+extern crate std;
+
+use std::borrow::Cow;
+use std::future::Future;
+use std::task::{Context, Poll, Waker};
+
+use super::def::*;
+use super::def::OpcodeRequest::*;
+use super::def::CqlRequestBody::*;
+use super::def::RCErrorType::*;
+use super::def::CqlResponseBody::*;
+use super::client::{CQL_MAX_SUPPORTED_VERSION, choose_cql_version, choose_protocol_version, choose_compression};
+
+///
+/// Connection as seen by the handshake, implemented by the blocking and the async clients.
+///
+pub(crate) trait Transport {
+    /// Replaces the current connection, if any, with a new one speaking `version`
+    async fn reconnect(&mut self, version: u8) -> RCResult<()>;
+
+    /// Sends a request and waits for its response. Requests are sent one at a time during the handshake
+    async fn roundtrip(&mut self, request: &mut CqlRequest<'_>) -> RCResult<CqlResponse>;
+
+    /// Decompresses the responses flagged as compressed from now on, before any request gets compressed
+    fn expect_compression(&mut self, compression: Compression);
+
+    /// Compresses every frame from now on, and from protocol v5 on wraps them in segments
+    fn start_framing(&mut self, compression: Compression);
+}
+
+///
+/// Negotiates the protocol version, CQL version and compression of a new connection, then starts it up and
+/// authenticates. Protocol versions are tried from the highest one down, on a new connection each time.
+/// Timeouts are not retried, as an unresponsive server would time out with every protocol version.
+/// Returns the options supported by the server, or an error listing why each protocol version failed.
+///
+pub(crate) async fn handshake<T: Transport>(transport: &mut T, creds: Option<&Vec<CowStr>>, compression: &[Compression]) -> RCResult<CqlStringMultiMap> {
+    let mut version = CQL_MAX_SUPPORTED_VERSION;
+    let mut errors = vec![];
+
+    while version >= 0x01 {
+        try_rc!(transport.reconnect(version).await, "Error connecting");
+
+        let supported = match send_options(transport).await {
+            Ok(supported) => supported,
+            Err(e) if e.kind == TimeoutError => return Err(e),
+            Err(e) => {
+                errors.push(format!("v{}: {}", version, e.desc));
+                version -= 1;
+                continue;
+            }
+        };
+
+        // Go straight to the best protocol version when the server tells which ones it supports
+        match choose_protocol_version(&supported, version) {
+            Some(best) if best < version => {
+                version = best;
+                continue;
+            },
+            _ => ()
+        }
+
+        let cql_version = choose_cql_version(&supported);
+        let algorithm = choose_compression(&supported, compression, version);
+
+        match send_startup(transport, version, creds, cql_version, algorithm).await {
+            Ok(_) => return Ok(supported),
+            Err(e) if e.kind == TimeoutError => return Err(e),
+            Err(e) => errors.push(format!("v{}: {}", version, e.desc))
+        }
+        version -= 1;
+    }
+    Err(RCError::new(format!("Unable to find suitable protocol version (v1, v2, v3, v4, v5): {}", errors.join("; ")), ReadError))
+}
+
+async fn send_options<T: Transport>(transport: &mut T) -> RCResult<CqlStringMultiMap> {
+    let mut msg_options = CqlRequest {
+        flags: 0x00,
+        stream: 0x00,
+        opcode: OpcodeOptions,
+        body: RequestOptions,
+    };
+
+    let response = try_rc!(transport.roundtrip(&mut msg_options).await, "Error reading options");
+    match response.body {
+        ResponseSupported(supported) => Ok(supported),
+        ResponseError(_, ref msg) => Err(RCError::new(format!("Error reading options: {}", msg), ReadError)),
+        _ => Err(RCError::new("Response does not contain supported options", ReadError))
+    }
+}
+
+async fn send_startup<T: Transport>(transport: &mut T, version: u8, creds: Option<&Vec<CowStr>>,
+                                    cql_version: CowStr, compression: Compression) -> RCResult<()> {
+    let mut body = CqlStringMap {
+        pairs:vec![CqlPair{key: Cow::Borrowed("CQL_VERSION"), value: cql_version}],
+    };
+    if compression != Compression::None {
+        body.pairs.push(CqlPair{key: Cow::Borrowed("COMPRESSION"), value: Cow::Borrowed(compression.as_str())});
+    }
+    let mut msg_startup = CqlRequest {
+        flags: 0x00,
+        stream: 0x00,
+        opcode: OpcodeStartup,
+        body: RequestStartup(body),
+    };
+
+    // Up to v4 the server may already compress its response to STARTUP, v5 never compresses it
+    if version < 5 {
+        transport.expect_compression(compression);
+    }
+    let response = try_rc!(transport.roundtrip(&mut msg_startup).await, "Error sending startup message");
+
+    // Compression, and from v5 on segments, apply to every message after READY or AUTHENTICATE
+    match response.body {
+        ResponseReady | ResponseAuthenticate(_) => transport.start_framing(compression),
+        _ => ()
+    }
+
+    match response.body {
+        ResponseReady => Ok(()),
+        ResponseAuthenticate(authenticator) => {
+            if !approve_authenticator(&authenticator) {
+                return Err(RCError::new(format!("Unexpected authenticator: {}", authenticator), ReadError))
+            }
+            let cred = match creds {
+                Some(cred) => cred,
+                None => return Err(RCError::new("Credential should be provided for authentication", ReadError))
+            };
+            if version < 2 {
+                return Err(RCError::new("Authentication is not supported for v1 protocol", ReadError))
+            }
+            let mut msg_auth = CqlRequest {
+                flags: 0x00,
+                stream: 0x00,
+                opcode: OpcodeAuthResponse,
+                body: RequestAuthResponse(make_token(cred)),
+            };
+            let response = try_rc!(transport.roundtrip(&mut msg_auth).await, "Error sending authentication response");
+            match response.body {
+                ResponseAuthSuccess(_) => Ok(()),
+                ResponseError(_, ref msg) => Err(RCError::new(format!("Error in authentication: {}", msg), ReadError)),
+                _ => Err(RCError::new("Server returned unknown message", ReadError))
+            }
+        },
+        ResponseError(_, ref msg) => Err(RCError::new(format!("Error connecting: {}", msg), ReadError)),
+        _ => Err(RCError::new("Wrong response to startup", ReadError))
+    }
+}
+
+fn approve_authenticator(authenticator: &CowStr) -> bool {
+    authenticator == "org.apache.cassandra.auth.PasswordAuthenticator"
+}
+
+///
+/// Makes an authentication response token that is compatible with PasswordAuthenticator.
+///
+fn make_token(creds: &Vec<CowStr>) -> Vec<u8> {
+    let mut token : Vec<u8> = Vec::new();
+    for cred in creds {
+        token.push(0);
+        token.extend(cred.as_bytes());
+    }
+    return token;
+}
+
+///
+/// Runs a handshake over a blocking transport. Such a transport never yields, so the handshake
+/// completes in a single poll and needs no runtime.
+///
+pub(crate) fn block_on<T, F: Future<Output = RCResult<T>>>(future: F) -> RCResult<T> {
+    let mut future = std::pin::pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    match future.as_mut().poll(&mut cx) {
+        Poll::Ready(output) => output,
+        Poll::Pending => Err(RCError::new("A blocking transport yielded during the handshake", GenericError))
+    }
+}
@@ -2,23 +2,62 @@
 #![crate_type = "rlib"]
 #![crate_type = "dylib"]
 
+// Conventions the code base follows throughout: `field: field` initialization, explicit `return`,
+// `&(ref a, ref b)` patterns, nested matches on optional values and `Cql` prefixed variants
+#![allow(clippy::redundant_field_names, clippy::needless_return, clippy::needless_borrowed_reference,
+         clippy::collapsible_match, clippy::enum_variant_names)]
+
 #[macro_use] extern crate enum_primitive as ep;
 
 pub use client::connect;
+pub use client::connect_with_compression;
+pub use client::connect_with_options;
+pub use client::ConnectOptions;
 pub use def::Consistency;
 pub use def::BatchType;
+pub use def::Compression;
 pub use def::CqlValue;
+pub use def::CqlRow;
+pub use def::Decimal;
+pub use def::Duration;
+pub use def::QueryParams;
+pub use def::QueryOptions;
+pub use def::BatchParams;
+pub use def::LwtResult;
 pub use def::CqlValue::CqlFloat;
 pub use def::CqlValue::CqlVarchar;
 pub use def::CQLList;
 pub use def::CQLMap;
+pub use def::Pair;
 pub use def::CQLSet;
+pub use def::CQLUdt;
+pub use def::CQLTuple;
+pub use def::CqlType;
+pub use def::CqlUdtType;
 pub use def::Query::QueryStr;
+pub use def::Query::QueryStrValues;
 pub use def::Query::QueryPrepared;
+pub use def::OpcodeRequest;
 pub use def::OpcodeResponse;
+pub use def::CqlRequest;
+pub use def::CqlRequestBody;
+pub use def::CqlResponse;
 pub use def::CqlResponseBody;
+pub use def::CqlPreparedStat;
+pub use def::CqlRows;
+pub use def::CqlMetadata;
 pub use def::RCResult;
+pub use def::CqlBytesSize;
 pub use def::RCError;
+pub use def::RCErrorType;
+pub use def::ServerError;
+pub use def::CqlEvent;
+pub use def::CqlEventType;
+pub use def::CqlSchemaChange;
+pub use def::TopologyChangeType;
+pub use def::StatusChangeType;
+pub use def::TraceSession;
+pub use def::TraceEvent;
 
 #[macro_export]
 macro_rules! try_bo(
@@ -26,11 +65,8 @@ macro_rules! try_bo(
         match $call {
             Ok(val) => val,
             Err(self::byteorder::Error::UnexpectedEOF) => return Err($crate::def::RCError::new(format!("{} -> {}", $msg, "Unexpected EOF"), $crate::def::RCErrorType::IOError)),
-            Err(self::byteorder::Error::Io(ref err)) => {
-            	use std::error::Error;
-            	return Err($crate::def::RCError::new(format!("{} -> {}", $msg, err.description()), $crate::def::RCErrorType::IOError))
-            }
-        };
+            Err(self::byteorder::Error::Io(ref err)) => return Err($crate::def::RCError::new(format!("{} -> {}", $msg, err), $crate::def::RCErrorType::IOError))
+        }
     }
 );
 
@@ -39,11 +75,8 @@ macro_rules! try_io(
     ($call: expr, $msg: expr) => {
         match $call {
             Ok(val) => val,
-            Err(ref err) => {
-            	use std::error::Error;
-            	return Err(RCError::new(format!("{} -> {}", $msg, err.description()), RCErrorType::IOError))
-            }
-        };
+            Err(ref err) => return Err(RCError::new(format!("{} -> {}", $msg, err), RCErrorType::IOError))
+        }
     }
 );
 
@@ -53,8 +86,8 @@ macro_rules! try_rc(
     ($call: expr, $msg: expr) => {
         match $call {
             Ok(val) => val,
-            Err(ref err) => return Err($crate::def::RCError::new(format!("{} -> {}", $msg, err.description()), $crate::def::RCErrorType::IOError))
-        };
+            Err(ref err) => return Err($crate::def::RCError::new(format!("{} -> {}", $msg, err.description()), err.kind))
+        }
     }
 );
 
@@ -64,7 +97,7 @@ macro_rules! try_rc_length(
             Ok(-1) => return Ok(None),
             Ok(val) => val,
             Err(ref err) => return Err($crate::def::RCError::new(format!("{} -> {}", $msg, err.description()), $crate::def::RCErrorType::IOError))
-        };
+        }
     }
 );
 
@@ -76,7 +109,7 @@ macro_rules! try_rc_noption(
                 Some(val) => val
             },
             Err(ref err) => return Err($crate::def::RCError::new(format!("{} -> {}", $msg, err.description()), $crate::def::RCErrorType::IOError))
-        };
+        }
     }
 );
 
@@ -88,6 +121,14 @@ macro_rules! CowStr_tuple_void(
 );
 
 mod def;
-mod reader;
-mod serialize;
+pub mod reader;
+pub mod serialize;
+pub mod compression;
+mod frame;
+mod handshake;
+pub mod timestamp;
+pub mod datetime;
+pub mod segment;
 pub mod client;
+#[cfg(feature = "async")]
+pub mod async_client;
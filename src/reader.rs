@@ -8,18 +8,16 @@ use super::def::CqlValue::*;
 use super::def::CqlValueType::*;
 use super::def::CqlResponseBody::*;
 use super::def::RCErrorType::*;
-use super::def::CqlBytesSize::*;
 use super::def::KindResult::*;
 use super::def::OpcodeResponse::*;
+use super::compression::decompress;
 
 use self::uuid::Uuid;
 use std::net::{Ipv4Addr, Ipv6Addr};
 use std::borrow::{Cow, ToOwned};
-use std::io::{Read, Write, Cursor};
-use self::byteorder::{ReadBytesExt, BigEndian, LittleEndian};
+use std::io::{Read, Cursor};
+use self::byteorder::{ReadBytesExt, BigEndian};
 use std::mem::size_of;
-use std::path::Path;
-use std::error::Error;
 use ep::FromPrimitive;
 
 pub trait CqlReader {
@@ -28,29 +26,50 @@ pub trait CqlReader {
     fn read_cql_bytes_length_fixed(&mut self, val_type: CqlBytesSize, length: i32) -> RCResult<i32>;
 
     fn read_cql_str(&mut self, val_type: CqlBytesSize) -> RCResult<Option<CowStr>>;
+    fn read_cql_string_list(&mut self) -> RCResult<Vec<CowStr>>;
+    fn read_cql_string_multimap(&mut self) -> RCResult<CqlStringMultiMap>;
+    fn read_cql_bytes_map(&mut self) -> RCResult<CqlBytesMap>;
     fn read_cql_f32(&mut self, val_type: CqlBytesSize) -> RCResult<Option<f32>>;
     fn read_cql_f64(&mut self, val_type: CqlBytesSize) -> RCResult<Option<f64>>;
+    fn read_cql_i8(&mut self, val_type: CqlBytesSize) -> RCResult<Option<i8>>;
+    fn read_cql_i16(&mut self, val_type: CqlBytesSize) -> RCResult<Option<i16>>;
     fn read_cql_i32(&mut self, val_type: CqlBytesSize) -> RCResult<Option<i32>>;
+    fn read_cql_u32(&mut self, val_type: CqlBytesSize) -> RCResult<Option<u32>>;
     fn read_cql_i64(&mut self, val_type: CqlBytesSize) -> RCResult<Option<i64>>;
     fn read_cql_u64(&mut self, val_type: CqlBytesSize) -> RCResult<Option<u64>>;
     fn read_cql_blob(&mut self, val_type: CqlBytesSize) -> RCResult<Option<Vec<u8>>>;
+    fn read_cql_varint(&mut self, val_type: CqlBytesSize) -> RCResult<Option<num::BigInt>>;
+    fn read_cql_decimal(&mut self, val_type: CqlBytesSize) -> RCResult<Option<Decimal>>;
+    fn read_cql_vint(&mut self) -> RCResult<i64>;
+    fn read_cql_duration(&mut self, val_type: CqlBytesSize) -> RCResult<Option<Duration>>;
     fn read_cql_boolean(&mut self, val_type: CqlBytesSize) -> RCResult<Option<bool>>;
     fn read_cql_uuid(&mut self, val_type: CqlBytesSize) -> RCResult<Option<Uuid>>;
+    fn read_cql_raw_uuid(&mut self) -> RCResult<Uuid>;
     fn read_cql_inet(&mut self, val_type: CqlBytesSize) -> RCResult<Option<IpAddr>>;
-
-    fn read_cql_list(&mut self, col_meta: &CqlColMetadata, value_size: CqlBytesSize) -> RCResult<Option<CQLList>>;
-    fn read_cql_set(&mut self, col_meta: &CqlColMetadata, value_size: CqlBytesSize) -> RCResult<Option<CQLSet>>;
-    fn read_cql_map(&mut self, col_meta: &CqlColMetadata, value_size: CqlBytesSize) -> RCResult<Option<CQLMap>>;
-
-    fn read_cql_metadata(&mut self) -> RCResult<CqlMetadata>;
+    fn read_cql_inet_addr(&mut self) -> RCResult<IpAddr>;
+    fn read_cql_inet_port(&mut self) -> RCResult<(IpAddr, i32)>;
+    fn read_cql_server_error(&mut self, code: u32, version: u8) -> RCResult<ServerError>;
+
+    fn read_cql_list(&mut self, elem_type: &CqlType, value_size: CqlBytesSize) -> RCResult<Option<CQLList>>;
+    fn read_cql_set(&mut self, elem_type: &CqlType, value_size: CqlBytesSize) -> RCResult<Option<CQLSet>>;
+    fn read_cql_map(&mut self, key_type: &CqlType, value_type: &CqlType, value_size: CqlBytesSize) -> RCResult<Option<CQLMap>>;
+    fn read_cql_udt(&mut self, udt: &CqlUdtType, value_size: CqlBytesSize) -> RCResult<Option<CQLUdt>>;
+    fn read_cql_tuple(&mut self, types: &[CqlType], value_size: CqlBytesSize) -> RCResult<Option<CQLTuple>>;
+    fn read_cql_type(&mut self) -> RCResult<CqlType>;
+
+    fn read_cql_metadata(&mut self, with_pk_indices: bool) -> RCResult<CqlMetadata>;
+    fn read_cql_schema_change(&mut self, version: u8) -> RCResult<CqlSchemaChange>;
+    fn read_cql_event(&mut self, version: u8) -> RCResult<CqlEvent>;
     fn read_cql_frame_header(&mut self, version: u8) -> RCResult<CqlFrameHeader>;
-    fn read_cql_response(&mut self, version: u8) -> RCResult<CqlResponse>;
-    fn read_cql_rows(&mut self, collection_size: CqlBytesSize) -> RCResult<CqlRows>;
+    fn read_cql_response_body(&mut self, header: CqlFrameHeader, version: u8, compression: Compression,
+                              result_meta: Option<&CqlMetadata>) -> RCResult<CqlResponse>;
+    fn read_cql_rows(&mut self, collection_size: CqlBytesSize, result_meta: Option<&CqlMetadata>) -> RCResult<CqlRows>;
 
     fn read_cql_skip(&mut self, val_type: CqlBytesSize) -> RCResult<()>;
 
     fn read_cql_value(&mut self, col_meta: &CqlColMetadata, collection_size: CqlBytesSize) -> RCResult<CqlValue>;
     fn read_cql_value_single(&mut self, col_type: &CqlValueType, value_size: CqlBytesSize) -> RCResult<CqlValue>;
+    fn read_cql_typed_value(&mut self, col_type: &CqlType, value_size: CqlBytesSize) -> RCResult<CqlValue>;
 }
 
 
@@ -90,13 +109,49 @@ impl<T: std::io::Read> CqlReader for T {
     }
 
     fn read_cql_str(&mut self, val_type: CqlBytesSize) -> RCResult<Option<CowStr>> {
-        let vec_u8 = try_rc!(self.read_cql_bytes(val_type), "Error reading string data");
+        let len = try_rc_length!(self.read_cql_bytes_length(val_type), "Error reading string length");
+        if len < 0 {
+            return Ok(None)
+        }
+        let mut vec_u8 = Vec::with_capacity(len as usize);
+        try_io!(std::io::copy(&mut self.take(len as u64), &mut vec_u8), "Error reading string data");
         match std::str::from_utf8(&vec_u8) {
             Ok(s) => Ok(Some(Cow::Owned(s.to_owned()))),
             Err(_) => Err(RCError::new("Error reading string, invalid utf8 sequence", RCErrorType::ReadError))
         }     
     }
 
+    fn read_cql_string_list(&mut self) -> RCResult<Vec<CowStr>> {
+        let len = try_bo!(self.read_u16::<BigEndian>(), "Error reading string list length");
+        let mut list = Vec::with_capacity(len as usize);
+        for _ in 0 .. len {
+            list.push(try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading string list element"));
+        }
+        Ok(list)
+    }
+
+    fn read_cql_string_multimap(&mut self) -> RCResult<CqlStringMultiMap> {
+        let len = try_bo!(self.read_u16::<BigEndian>(), "Error reading string multimap length");
+        let mut map = CqlStringMultiMap::new();
+        for _ in 0 .. len {
+            let key = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading string multimap key");
+            let values = try_rc!(self.read_cql_string_list(), "Error reading string multimap values");
+            map.insert(key, values);
+        }
+        Ok(map)
+    }
+
+    fn read_cql_bytes_map(&mut self) -> RCResult<CqlBytesMap> {
+        let len = try_bo!(self.read_u16::<BigEndian>(), "Error reading bytes map length");
+        let mut map = CqlBytesMap::new();
+        for _ in 0 .. len {
+            let key = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading bytes map key");
+            let value = try_rc!(self.read_cql_bytes(CqlBytesSize::Cqli32), "Error reading bytes map value");
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+
     fn read_cql_f32(&mut self, val_type: CqlBytesSize) -> RCResult<Option<f32>> {
         try_rc_length!(self.read_cql_bytes_length_fixed(val_type, size_of::<f32>() as i32), "Error reading bytes (float) length");
         Ok(Some(try_bo!(self.read_f32::<BigEndian>(), "Error reading float (float)")))  
@@ -107,13 +162,28 @@ impl<T: std::io::Read> CqlReader for T {
         Ok(Some(try_bo!(self.read_f64::<BigEndian>(), "Error reading double (double)")))    
     }
 
+    fn read_cql_i8(&mut self, val_type: CqlBytesSize) -> RCResult<Option<i8>> {
+        try_rc_length!(self.read_cql_bytes_length_fixed(val_type, size_of::<i8>() as i32), "Error reading bytes (tinyint) length");
+        Ok(Some(try_bo!(self.read_i8(), "Error reading tinyint (i8)")))
+    }
+
+    fn read_cql_i16(&mut self, val_type: CqlBytesSize) -> RCResult<Option<i16>> {
+        try_rc_length!(self.read_cql_bytes_length_fixed(val_type, size_of::<i16>() as i32), "Error reading bytes (smallint) length");
+        Ok(Some(try_bo!(self.read_i16::<BigEndian>(), "Error reading smallint (i16)")))
+    }
+
     fn read_cql_i32(&mut self, val_type: CqlBytesSize) -> RCResult<Option<i32>> {
         try_rc_length!(self.read_cql_bytes_length(val_type), "Error reading bytes (int) length");
         Ok(Some(try_bo!(self.read_i32::<BigEndian>(), "Error reading int (i32)")))
     }
 
+    fn read_cql_u32(&mut self, val_type: CqlBytesSize) -> RCResult<Option<u32>> {
+        try_rc_length!(self.read_cql_bytes_length_fixed(val_type, size_of::<u32>() as i32), "Error reading bytes (date) length");
+        Ok(Some(try_bo!(self.read_u32::<BigEndian>(), "Error reading date (u32)")))
+    }
+
     fn read_cql_i64(&mut self, val_type: CqlBytesSize) -> RCResult<Option<i64>> {
-        try_rc_length!(self.read_cql_bytes_length(val_type), "Error reading bytes (long) length");
+        try_rc_length!(self.read_cql_bytes_length_fixed(val_type, size_of::<i64>() as i32), "Error reading bytes (long) length");
         Ok(Some(try_bo!(self.read_i64::<BigEndian>(), "Error reading long (i64)")))
     }
 
@@ -124,7 +194,62 @@ impl<T: std::io::Read> CqlReader for T {
     
     fn read_cql_blob(&mut self, val_type: CqlBytesSize) -> RCResult<Option<Vec<u8>>> {
         let len = try_rc_length!(self.read_cql_bytes_length(val_type), "Error reading blob length");
-        Ok(Some(try_rc!(self.read_cql_bytes(val_type), "Error reading string data")))
+        let mut buf = Vec::with_capacity(len as usize);
+        try_io!(std::io::copy(&mut self.take(len as u64), &mut buf), "Error reading blob data");
+        Ok(Some(buf))
+    }
+
+    // Two's complement, big-endian and of any length
+    fn read_cql_varint(&mut self, val_type: CqlBytesSize) -> RCResult<Option<num::BigInt>> {
+        let vec = try_rc!(self.read_cql_bytes(val_type), "Error reading varint data");
+        if vec.is_empty() {
+            return Ok(None)
+        }
+        Ok(Some(num::BigInt::from_signed_bytes_be(&vec)))
+    }
+
+    // The scale as an int followed by the unscaled value as a varint
+    fn read_cql_decimal(&mut self, val_type: CqlBytesSize) -> RCResult<Option<Decimal>> {
+        let vec = try_rc!(self.read_cql_bytes(val_type), "Error reading decimal data");
+        if vec.is_empty() {
+            return Ok(None)
+        }
+        if vec.len() < 4 {
+            return Err(RCError::new(format!("Error reading decimal, invalid length {}", vec.len()), ReadError))
+        }
+        let scale = try_bo!(Cursor::new(&vec[.. 4]).read_i32::<BigEndian>(), "Error reading decimal scale");
+        Ok(Some(Decimal::new(num::BigInt::from_signed_bytes_be(&vec[4 ..]), scale)))
+    }
+
+    // The number of leading 1 bits of the first byte is the number of extra bytes, the value is zigzag encoded
+    fn read_cql_vint(&mut self) -> RCResult<i64> {
+        let first = try_bo!(self.read_u8(), "Error reading vint");
+        let extra = (!first).leading_zeros();
+        let mut value = if extra >= 8 { 0 } else { (first & (0xffu8 >> extra)) as u64 };
+        for _ in 0 .. extra {
+            value = (value << 8) | try_bo!(self.read_u8(), "Error reading vint") as u64;
+        }
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    // Months, days and nanoseconds as vints
+    fn read_cql_duration(&mut self, val_type: CqlBytesSize) -> RCResult<Option<Duration>> {
+        let vec = try_rc!(self.read_cql_bytes(val_type), "Error reading duration data");
+        if vec.is_empty() {
+            return Ok(None)
+        }
+        let mut reader = Cursor::new(vec);
+        let months = try_rc!(reader.read_cql_vint(), "Error reading duration months");
+        let days = try_rc!(reader.read_cql_vint(), "Error reading duration days");
+        let nanoseconds = try_rc!(reader.read_cql_vint(), "Error reading duration nanoseconds");
+        if months < i32::MIN as i64 || months > i32::MAX as i64 || days < i32::MIN as i64 || days > i32::MAX as i64 {
+            return Err(RCError::new(format!("Error reading duration, months ({}) or days ({}) out of range", months, days), ReadError))
+        }
+        let duration = Duration::new(months as i32, days as i32, nanoseconds);
+        if !duration.is_valid() {
+            return Err(RCError::new(format!("Error reading duration, mixed signs ({:?})", duration), ReadError))
+        }
+        Ok(Some(duration))
     }
 
     fn read_cql_boolean(&mut self, val_type: CqlBytesSize) -> RCResult<Option<bool>> {
@@ -140,16 +265,21 @@ impl<T: std::io::Read> CqlReader for T {
         if len != 16 {
             return Err(RCError::new("Invalid uuid length", RCErrorType::ReadError))  
         }
-        let vec_u8 = try_rc!(self.read_cql_bytes(val_type), "Error reading uuid data");
-        match Uuid::from_bytes(&vec_u8) {
-            Some(u) => Ok(Some(u)),
+        Ok(Some(try_rc!(self.read_cql_raw_uuid(), "Error reading uuid data")))
+    }
+
+    fn read_cql_raw_uuid(&mut self) -> RCResult<Uuid> {
+        let mut data = [0; 16];
+        try_io!(self.read_exact(&mut data), "Error reading uuid data");
+        match Uuid::from_bytes(&data).ok() {
+            Some(u) => Ok(u),
             None => Err(RCError::new("Invalid uuid", RCErrorType::ReadError))
-        }      
+        }
     }
 
     fn read_cql_inet(&mut self, val_type: CqlBytesSize) -> RCResult<Option<IpAddr>> {
         let vec = try_rc!(self.read_cql_bytes(val_type), "Error reading value data");
-        if vec.len() == 0 {
+        if vec.is_empty() {
             Ok(None)
         } else if vec.len() == 4 {
             Ok(Some(IpAddr::Ipv4(Ipv4Addr::new(vec[0], vec[1], vec[2], vec[3]))))
@@ -165,8 +295,123 @@ impl<T: std::io::Read> CqlReader for T {
         }
     }
 
-    fn read_cql_list(&mut self, col_meta: &CqlColMetadata, value_size: CqlBytesSize) -> RCResult<Option<CQLList>> {
-        try_bo!(self.read_i32::<BigEndian>(), "Error reading list size");
+    fn read_cql_inet_port(&mut self) -> RCResult<(IpAddr, i32)> {
+        let address = try_rc!(self.read_cql_inet_addr(), "Error reading inet address");
+        let port = try_bo!(self.read_i32::<BigEndian>(), "Error reading inet port");
+        Ok((address, port))
+    }
+
+    fn read_cql_inet_addr(&mut self) -> RCResult<IpAddr> {
+        let size = try_bo!(self.read_u8(), "Error reading inet size");
+        let mut data = [0u8; 16];
+        let address = match size {
+            4 => {
+                try_io!(self.read_exact(&mut data[..4]), "Error reading inet address (ipv4)");
+                IpAddr::Ipv4(Ipv4Addr::new(data[0], data[1], data[2], data[3]))
+            },
+            16 => {
+                try_io!(self.read_exact(&mut data), "Error reading inet address (ipv6)");
+                let mut segments = [0u16; 8];
+                for i in 0 .. 8 {
+                    segments[i] = ((data[2 * i] as u16) << 8) + data[2 * i + 1] as u16;
+                }
+                IpAddr::Ipv6(Ipv6Addr::new(segments[0], segments[1], segments[2], segments[3],
+                                           segments[4], segments[5], segments[6], segments[7]))
+            },
+            _ => return Err(RCError::new(format!("Invalid inet size ({})", size), ReadError))
+        };
+        Ok(address)
+    }
+
+    fn read_cql_server_error(&mut self, code: u32, version: u8) -> RCResult<ServerError> {
+        let error = match code {
+            0x0000 => ServerError::ServerFailure,
+            0x000A => ServerError::ProtocolError,
+            0x0100 => ServerError::BadCredentials,
+            0x1000 => {
+                let consistency = cql_consistency(try_bo!(self.read_u16::<BigEndian>(), "Error reading unavailable error (consistency)"));
+                let required = try_bo!(self.read_i32::<BigEndian>(), "Error reading unavailable error (required)");
+                let alive = try_bo!(self.read_i32::<BigEndian>(), "Error reading unavailable error (alive)");
+                ServerError::Unavailable { consistency: consistency, required: required, alive: alive }
+            },
+            0x1001 => ServerError::Overloaded,
+            0x1002 => ServerError::IsBootstrapping,
+            0x1003 => ServerError::TruncateError,
+            0x1100 | 0x1200 | 0x1300 | 0x1500 | 0x1700 => {
+                let consistency = cql_consistency(try_bo!(self.read_u16::<BigEndian>(), "Error reading error (consistency)"));
+                let received = try_bo!(self.read_i32::<BigEndian>(), "Error reading error (received)");
+                let block_for = try_bo!(self.read_i32::<BigEndian>(), "Error reading error (block for)");
+                // Failures carry the reason of each replica from v5 on, only their number before
+                let (num_failures, reasons) = if code == 0x1300 || code == 0x1500 {
+                    if version >= 5 {
+                        let n = try_bo!(self.read_i32::<BigEndian>(), "Error reading error (reason map length)");
+                        let mut reasons = vec![];
+                        for _ in 0 .. n {
+                            let endpoint = try_rc!(self.read_cql_inet_addr(), "Error reading error (reason endpoint)");
+                            let reason = try_bo!(self.read_u16::<BigEndian>(), "Error reading error (reason code)");
+                            reasons.push((endpoint, reason));
+                        }
+                        (n, reasons)
+                    } else {
+                        (try_bo!(self.read_i32::<BigEndian>(), "Error reading error (number of failures)"), vec![])
+                    }
+                } else {
+                    (0, vec![])
+                };
+                match code {
+                    0x1100 => {
+                        let write_type = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading write timeout error (write type)");
+                        let contentions = if version >= 5 && write_type == "CAS" {
+                            Some(try_bo!(self.read_u16::<BigEndian>(), "Error reading write timeout error (contentions)"))
+                        } else {
+                            None
+                        };
+                        ServerError::WriteTimeout { consistency: consistency, received: received, block_for: block_for,
+                                                    write_type: write_type, contentions: contentions }
+                    },
+                    0x1200 => {
+                        let data_present = try_bo!(self.read_u8(), "Error reading read timeout error (data present)") != 0;
+                        ServerError::ReadTimeout { consistency: consistency, received: received, block_for: block_for, data_present: data_present }
+                    },
+                    0x1300 => {
+                        let data_present = try_bo!(self.read_u8(), "Error reading read failure error (data present)") != 0;
+                        ServerError::ReadFailure { consistency: consistency, received: received, block_for: block_for,
+                                                   num_failures: num_failures, reasons: reasons, data_present: data_present }
+                    },
+                    0x1500 => {
+                        let write_type = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading write failure error (write type)");
+                        ServerError::WriteFailure { consistency: consistency, received: received, block_for: block_for,
+                                                    num_failures: num_failures, reasons: reasons, write_type: write_type }
+                    },
+                    _ => ServerError::CasWriteUnknown { consistency: consistency, received: received, block_for: block_for }
+                }
+            },
+            0x1400 => {
+                let keyspace = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading function failure error (keyspace)");
+                let function = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading function failure error (function)");
+                let arg_types = try_rc!(self.read_cql_string_list(), "Error reading function failure error (argument types)");
+                ServerError::FunctionFailure { keyspace: keyspace, function: function, arg_types: arg_types }
+            },
+            0x1600 => ServerError::CdcWriteFailure,
+            0x2000 => ServerError::SyntaxError,
+            0x2100 => ServerError::Unauthorized,
+            0x2200 => ServerError::Invalid,
+            0x2300 => ServerError::ConfigError,
+            0x2400 => {
+                let keyspace = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading already exists error (keyspace)");
+                let table = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading already exists error (table)");
+                ServerError::AlreadyExists { keyspace: keyspace, table: table }
+            },
+            0x2500 => ServerError::Unprepared { id: try_rc!(self.read_cql_bytes(CqlBytesSize::Cqli16), "Error reading unprepared error (id)") },
+            _ => ServerError::Unknown(code)
+        };
+        Ok(error)
+    }
+
+    fn read_cql_list(&mut self, elem_type: &CqlType, value_size: CqlBytesSize) -> RCResult<Option<CQLList>> {
+        if try_bo!(self.read_i32::<BigEndian>(), "Error reading list size") < 0 {
+            return Ok(None)
+        }
         let len = match value_size {
             CqlBytesSize::Cqli32 => try_bo!(self.read_i32::<BigEndian>(), "Error reading list length"),
             CqlBytesSize::Cqli16 => try_bo!(self.read_i16::<BigEndian>(), "Error reading list length") as i32
@@ -174,14 +419,16 @@ impl<T: std::io::Read> CqlReader for T {
 
         let mut list: CQLList = vec![];
         for _ in 0 .. len {
-            let col = try_rc!(self.read_cql_value_single(&col_meta.col_type_aux1, value_size), "Error reading list value");
+            let col = try_rc!(self.read_cql_typed_value(elem_type, value_size), "Error reading list value");
             list.push(col);
         }
         Ok(Some(list))
     }
 
-    fn read_cql_set(&mut self, col_meta: &CqlColMetadata, value_size: CqlBytesSize) -> RCResult<Option<CQLSet>> {
-        try_bo!(self.read_i32::<BigEndian>(), "Error reading set size");
+    fn read_cql_set(&mut self, elem_type: &CqlType, value_size: CqlBytesSize) -> RCResult<Option<CQLSet>> {
+        if try_bo!(self.read_i32::<BigEndian>(), "Error reading set size") < 0 {
+            return Ok(None)
+        }
         let len = match value_size {
             CqlBytesSize::Cqli32 => try_bo!(self.read_i32::<BigEndian>(), "Error reading list length"),
             CqlBytesSize::Cqli16 => try_bo!(self.read_i16::<BigEndian>(), "Error reading list length") as i32
@@ -189,14 +436,16 @@ impl<T: std::io::Read> CqlReader for T {
 
         let mut set: CQLSet = vec![];
         for _ in 0 .. len {
-            let col = try_rc!(self.read_cql_value_single(&col_meta.col_type_aux1, value_size), "Error reading set value");
+            let col = try_rc!(self.read_cql_typed_value(elem_type, value_size), "Error reading set value");
             set.push(col);
         }
         Ok(Some(set))
     }
 
-    fn read_cql_map(&mut self, col_meta: &CqlColMetadata, value_size: CqlBytesSize) -> RCResult<Option<CQLMap>> {
-        try_bo!(self.read_i32::<BigEndian>(), "Error reading map size");
+    fn read_cql_map(&mut self, key_type: &CqlType, value_type: &CqlType, value_size: CqlBytesSize) -> RCResult<Option<CQLMap>> {
+        if try_bo!(self.read_i32::<BigEndian>(), "Error reading map size") < 0 {
+            return Ok(None)
+        }
         let len = match value_size {
             CqlBytesSize::Cqli32 => try_bo!(self.read_i32::<BigEndian>(), "Error reading list length"),
             CqlBytesSize::Cqli16 => try_bo!(self.read_i16::<BigEndian>(), "Error reading list length") as i32
@@ -204,23 +453,128 @@ impl<T: std::io::Read> CqlReader for T {
 
         let mut map: CQLMap = vec![];
         for _ in 0 .. len {
-            let key = try_rc!(self.read_cql_value_single(&col_meta.col_type_aux1, value_size), "Error reading map key");
-            let value = try_rc!(self.read_cql_value_single(&col_meta.col_type_aux2, value_size), "Error reading map value");
+            let key = try_rc!(self.read_cql_typed_value(key_type, value_size), "Error reading map key");
+            let value = try_rc!(self.read_cql_typed_value(value_type, value_size), "Error reading map value");
             map.push(Pair { key: key, value: value});
         }
         Ok(Some(map))
     }
 
+    // Each field is a [bytes] value. Values written before fields were added to the type lack the trailing ones
+    fn read_cql_udt(&mut self, udt: &CqlUdtType, value_size: CqlBytesSize) -> RCResult<Option<CQLUdt>> {
+        let len = try_rc_length!(self.read_cql_bytes_length(value_size), "Error reading UDT length");
+        if len < 0 {
+            return Ok(None)
+        }
+        let mut data = Vec::with_capacity(len as usize);
+        try_io!(std::io::copy(&mut self.take(len as u64), &mut data), "Error reading UDT data");
+        let mut reader = Cursor::new(data);
+        let mut fields: CQLUdt = vec![];
+        for &(ref name, ref field_type) in udt.fields.iter() {
+            if reader.position() >= len as u64 {
+                break;
+            }
+            let value = try_rc!(reader.read_cql_typed_value(field_type, CqlBytesSize::Cqli32), "Error reading UDT field");
+            fields.push((name.clone(), value));
+        }
+        Ok(Some(fields))
+    }
+
+    // Each component is a [bytes] value, null ones have a negative length
+    fn read_cql_tuple(&mut self, types: &[CqlType], value_size: CqlBytesSize) -> RCResult<Option<CQLTuple>> {
+        let len = try_rc_length!(self.read_cql_bytes_length(value_size), "Error reading tuple length");
+        if len < 0 {
+            return Ok(None)
+        }
+        let mut data = Vec::with_capacity(len as usize);
+        try_io!(std::io::copy(&mut self.take(len as u64), &mut data), "Error reading tuple data");
+        let mut reader = Cursor::new(data);
+        let mut components: CQLTuple = Vec::with_capacity(types.len());
+        for component_type in types.iter() {
+            components.push(try_rc!(reader.read_cql_typed_value(component_type, CqlBytesSize::Cqli32), "Error reading tuple component"));
+        }
+        Ok(Some(components))
+    }
+
+    // [option] of the protocol: the id of the type, followed by the description of the types it is made of
+    fn read_cql_type(&mut self) -> RCResult<CqlType> {
+        let type_key = try_bo!(self.read_u16::<BigEndian>(), "Error reading type key");
+        match type_key {
+            0x0000 => Ok(CqlType::Custom(try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading custom type class"))),
+            0x0020 => Ok(CqlType::List(Box::new(try_rc!(self.read_cql_type(), "Error reading list type")))),
+            0x0021 => {
+                let key_type = try_rc!(self.read_cql_type(), "Error reading map key type");
+                let value_type = try_rc!(self.read_cql_type(), "Error reading map value type");
+                Ok(CqlType::Map(Box::new(key_type), Box::new(value_type)))
+            },
+            0x0022 => Ok(CqlType::Set(Box::new(try_rc!(self.read_cql_type(), "Error reading set type")))),
+            0x0030 => {
+                let keyspace = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading UDT keyspace");
+                let name = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading UDT name");
+                let count = try_bo!(self.read_u16::<BigEndian>(), "Error reading UDT field count");
+                let mut fields = Vec::with_capacity(count as usize);
+                for _ in 0 .. count {
+                    let field_name = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading UDT field name");
+                    let field_type = try_rc!(self.read_cql_type(), "Error reading UDT field type");
+                    fields.push((field_name, field_type));
+                }
+                Ok(CqlType::Udt(CqlUdtType { keyspace: keyspace, name: name, fields: fields }))
+            },
+            0x0031 => {
+                let count = try_bo!(self.read_u16::<BigEndian>(), "Error reading tuple component count");
+                let mut types = Vec::with_capacity(count as usize);
+                for _ in 0 .. count {
+                    types.push(try_rc!(self.read_cql_type(), "Error reading tuple component type"));
+                }
+                Ok(CqlType::Tuple(types))
+            },
+            _ => Ok(CqlType::Native(cql_column_type(type_key)))
+        }
+    }
+
     fn read_cql_skip(&mut self, val_type: CqlBytesSize) -> RCResult<()> {
-        let len = try_rc!(self.read_cql_bytes_length(val_type), "Error reading value length");
         try_rc!(self.read_cql_bytes(val_type), "Error reading value data");
         Ok(())     
     }
 
-    fn read_cql_metadata(&mut self) -> RCResult<CqlMetadata> {
+    fn read_cql_metadata(&mut self, with_pk_indices: bool) -> RCResult<CqlMetadata> {
         let flags = try_bo!(self.read_u32::<BigEndian>(), "Error reading flags");
         let column_count = try_bo!(self.read_u32::<BigEndian>(), "Error reading column count");
 
+        let paging_state = if flags & 0x0002 != 0 {
+            Some(try_rc!(self.read_cql_bytes(CqlBytesSize::Cqli32), "Error reading paging state"))
+        } else {
+            None
+        };
+
+        let new_metadata_id = if flags & 0x0008 != 0 {
+            Some(try_rc!(self.read_cql_bytes(CqlBytesSize::Cqli16), "Error reading new metadata id"))
+        } else {
+            None
+        };
+
+        // Executions with skip_metadata get no column specs, the ones cached at PREPARE time are used
+        if flags & 0x0004 != 0 {
+            return Ok(CqlMetadata {
+                flags: flags,
+                column_count: column_count,
+                keyspace: Cow::Borrowed(""),
+                table: Cow::Borrowed(""),
+                pk_indices: vec![],
+                paging_state: paging_state,
+                new_metadata_id: new_metadata_id,
+                row_metadata: vec![],
+            })
+        }
+
+        let mut pk_indices:Vec<u16> = vec![];
+        if with_pk_indices {
+            let pk_count = try_bo!(self.read_u32::<BigEndian>(), "Error reading partition key count");
+            for _ in 0u32 .. pk_count {
+                pk_indices.push(try_bo!(self.read_u16::<BigEndian>(), "Error reading partition key index"));
+            }
+        }
+
         let (ks, tb) =
         if flags & 0x0001 != 0 {
             let keyspace_str = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading keyspace name");
@@ -241,29 +595,21 @@ impl<T: std::io::Read> CqlReader for T {
                 (keyspace_str, table_str)
             };
             let col_name = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading column name");
-            let type_key = try_bo!(self.read_u16::<BigEndian>(), "Error reading type key");
-            let type_aux1 =
-            if type_key >= 0x20 {
-                let ctype = try_bo!(self.read_u16::<BigEndian>(), "Error reading list/set/map type");
-                cql_column_type(ctype)
-            } else {
-                CqlValueType::ColumnUnknown
-            };
-            let type_aux2 =
-            if type_key == 0x21 {
-                let ctype = try_bo!(self.read_u16::<BigEndian>(), "Error reading map type value");
-                cql_column_type(ctype)
-            } else {
-                CqlValueType::ColumnUnknown
+            let col_type = try_rc!(self.read_cql_type(), "Error reading column type");
+            let (type_aux1, type_aux2) = match col_type {
+                CqlType::List(ref elem_type) | CqlType::Set(ref elem_type) => (elem_type.value_type(), CqlValueType::ColumnUnknown),
+                CqlType::Map(ref key_type, ref value_type) => (key_type.value_type(), value_type.value_type()),
+                _ => (CqlValueType::ColumnUnknown, CqlValueType::ColumnUnknown)
             };
 
             row_metadata.push(CqlColMetadata {
                 keyspace: keyspace,
                 table: table,
                 col_name: col_name,
-                col_type: cql_column_type(type_key),
+                col_type: col_type.value_type(),
                 col_type_aux1: type_aux1,
-                col_type_aux2: type_aux2
+                col_type_aux2: type_aux2,
+                col_type_info: col_type
             });
         }
 
@@ -272,16 +618,30 @@ impl<T: std::io::Read> CqlReader for T {
             column_count: column_count,
             keyspace: ks,
             table: tb,
+            pk_indices: pk_indices,
+            paging_state: paging_state,
+            new_metadata_id: new_metadata_id,
             row_metadata: row_metadata,
         })
     }
 
+    // Column values are always [bytes], only the elements of collections are [short bytes] before v3
     fn read_cql_value(&mut self, col_meta: &CqlColMetadata, collection_size: CqlBytesSize) -> RCResult<CqlValue> {
-        match col_meta.col_type {
-            ColumnList => { Ok(CqlList(try_rc!(self.read_cql_list(col_meta, collection_size), "Error reading column value (list)"))) },
-            ColumnMap => { Ok(CqlMap(try_rc!(self.read_cql_map(col_meta, collection_size), "Error reading column value (map)"))) },
-            ColumnSet => { Ok(CqlSet(try_rc!(self.read_cql_set(col_meta, collection_size), "Error reading column value (set)"))) },
-            _ => self.read_cql_value_single(&col_meta.col_type, CqlBytesSize::Cqli32)
+        match col_meta.col_type_info {
+            CqlType::List(_) | CqlType::Map(_, _) | CqlType::Set(_) => self.read_cql_typed_value(&col_meta.col_type_info, collection_size),
+            _ => self.read_cql_typed_value(&col_meta.col_type_info, CqlBytesSize::Cqli32)
+        }
+    }
+
+    fn read_cql_typed_value(&mut self, col_type: &CqlType, value_size: CqlBytesSize) -> RCResult<CqlValue> {
+        match *col_type {
+            CqlType::List(ref elem_type) => Ok(CqlList(try_rc!(self.read_cql_list(elem_type, value_size), "Error reading value (list)"))),
+            CqlType::Map(ref key_type, ref value_type) => Ok(CqlMap(try_rc!(self.read_cql_map(key_type, value_type, value_size), "Error reading value (map)"))),
+            CqlType::Set(ref elem_type) => Ok(CqlSet(try_rc!(self.read_cql_set(elem_type, value_size), "Error reading value (set)"))),
+            CqlType::Udt(ref udt) => Ok(CqlUdt(try_rc!(self.read_cql_udt(udt, value_size), "Error reading value (UDT)"))),
+            CqlType::Tuple(ref types) => Ok(CqlTuple(try_rc!(self.read_cql_tuple(types, value_size), "Error reading value (tuple)"))),
+            CqlType::Custom(_) => self.read_cql_value_single(&ColumnCustom, value_size),
+            CqlType::Native(ref value_type) => self.read_cql_value_single(value_type, value_size)
         }
     }
 
@@ -304,21 +664,18 @@ impl<T: std::io::Read> CqlReader for T {
             ColumnBlob => Ok(CqlBlob(try_rc!(self.read_cql_blob(val_type), "Error reading column value (blob)"))),
             ColumnBoolean => Ok(CqlBoolean(try_rc!(self.read_cql_boolean(val_type), "Error reading column vaue (boolean)"))),
             ColumnCounter => Ok(CqlCounter(try_rc!(self.read_cql_i64(val_type), "Error reading column value (counter"))),
-            ColumnDecimal => {
-                try_rc!(self.read_cql_skip(val_type), "Error reading column value (decimal)");
-                println!("Decimal parse not implemented");
-                Ok(CqlUnknown)
-            },
+            ColumnDecimal => Ok(CqlDecimal(try_rc!(self.read_cql_decimal(val_type), "Error reading column value (decimal)"))),
             ColumnTimestamp => Ok(CqlTimestamp(try_rc!(self.read_cql_u64(val_type), "Error reading column value (timestamp)"))),
             ColumnUuid => Ok(CqlUuid(try_rc!(self.read_cql_uuid(val_type), "Error reading column value (uuid)"))),
-            ColumnVarint => {
-                try_rc!(self.read_cql_skip(val_type), "Error reading column value (varint)");
-                println!("Varint parse not implemented");
-                Ok(CqlUnknown)
-            },
+            ColumnVarint => Ok(CqlVarint(try_rc!(self.read_cql_varint(val_type), "Error reading column value (varint)"))),
             ColumnTimeUuid => Ok(CqlTimeUuid(try_rc!(self.read_cql_uuid(val_type), "Error reading column value (timeuuid)"))),
             ColumnInet => Ok(CqlInet(try_rc!(self.read_cql_inet(val_type), "Error reading column value (inet)"))),
-            CqlValueType::ColumnUnknown => panic!("Unknown column type !"),
+            ColumnDate => Ok(CqlDate(try_rc!(self.read_cql_u32(val_type), "Error reading column value (date)"))),
+            ColumnTime => Ok(CqlTime(try_rc!(self.read_cql_i64(val_type), "Error reading column value (time)"))),
+            ColumnSmallInt => Ok(CqlSmallInt(try_rc!(self.read_cql_i16(val_type), "Error reading column value (smallint)"))),
+            ColumnTinyInt => Ok(CqlTinyInt(try_rc!(self.read_cql_i8(val_type), "Error reading column value (tinyint)"))),
+            ColumnDuration => Ok(CqlDuration(try_rc!(self.read_cql_duration(val_type), "Error reading column value (duration)"))),
+            CqlValueType::ColumnUnknown => Err(RCError::new("Error reading column value (unknown column type)", ReadError)),
             _ => Err(RCError::new("Trying to read a non-single value type", ReadError))
         }
     }
@@ -359,10 +716,17 @@ impl<T: std::io::Read> CqlReader for T {
     // }
 
 
-    fn read_cql_rows(&mut self, collection_size: CqlBytesSize) -> RCResult<CqlRows> {
-        let metadata = try_rc!(self.read_cql_metadata(), "Error reading metadata");
+    fn read_cql_rows(&mut self, collection_size: CqlBytesSize, result_meta: Option<&CqlMetadata>) -> RCResult<CqlRows> {
+        let mut metadata = try_rc!(self.read_cql_metadata(false), "Error reading metadata");
         let rows_count = try_bo!(self.read_u32::<BigEndian>(), "Error reading metadata");
 
+        if metadata.flags & 0x0004 != 0 {
+            match result_meta {
+                Some(meta) => metadata.row_metadata = meta.row_metadata.clone(),
+                None => return Err(RCError::new("Rows without metadata and no cached result metadata", ReadError))
+            }
+        }
+
         let mut rows:Vec<CqlRow> = vec![];
         for _ in 0u32..rows_count {
             let mut row = CqlRow{ cols: vec![] };
@@ -379,14 +743,69 @@ impl<T: std::io::Read> CqlReader for T {
         })
     }
 
+    fn read_cql_schema_change(&mut self, version: u8) -> RCResult<CqlSchemaChange> {
+        let change = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading schema change (change)");
+        if version < 3 {
+            let keyspace = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading schema change (keyspace)");
+            let table = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading schema change (table)");
+            let target = if table.is_empty() { "KEYSPACE" } else { "TABLE" };
+            return Ok(CqlSchemaChange { change: change, target: Cow::Borrowed(target), keyspace: keyspace, name: table, arg_types: vec![] })
+        }
+
+        let target = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading schema change (target)");
+        let keyspace = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading schema change (keyspace)");
+        let name = if target != "KEYSPACE" {
+            try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading schema change (name)")
+        } else {
+            Cow::Borrowed("")
+        };
+        let arg_types = if target == "FUNCTION" || target == "AGGREGATE" {
+            try_rc!(self.read_cql_string_list(), "Error reading schema change (argument types)")
+        } else {
+            vec![]
+        };
+        Ok(CqlSchemaChange { change: change, target: target, keyspace: keyspace, name: name, arg_types: arg_types })
+    }
+
+    fn read_cql_event(&mut self, version: u8) -> RCResult<CqlEvent> {
+        let event_type = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading event type");
+        match &*event_type {
+            "TOPOLOGY_CHANGE" => {
+                let change = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading topology change");
+                let (address, port) = try_rc!(self.read_cql_inet_port(), "Error reading topology change (address)");
+                let change = match &*change {
+                    "NEW_NODE" => TopologyChangeType::NewNode,
+                    "REMOVED_NODE" => TopologyChangeType::RemovedNode,
+                    "MOVED_NODE" => TopologyChangeType::MovedNode,
+                    _ => return Err(RCError::new(format!("Unknown topology change: {}", change), ReadError))
+                };
+                Ok(CqlEvent::TopologyChange { change: change, address: address, port: port })
+            },
+            "STATUS_CHANGE" => {
+                let change = try_rc_noption!(self.read_cql_str(CqlBytesSize::Cqli16), "Error reading status change");
+                let (address, port) = try_rc!(self.read_cql_inet_port(), "Error reading status change (address)");
+                let change = match &*change {
+                    "UP" => StatusChangeType::Up,
+                    "DOWN" => StatusChangeType::Down,
+                    _ => return Err(RCError::new(format!("Unknown status change: {}", change), ReadError))
+                };
+                Ok(CqlEvent::StatusChange { change: change, address: address, port: port })
+            },
+            "SCHEMA_CHANGE" => {
+                Ok(CqlEvent::SchemaChange(try_rc!(self.read_cql_schema_change(version), "Error reading schema change event")))
+            },
+            _ => Err(RCError::new(format!("Unknown event type: {}", event_type), ReadError))
+        }
+    }
+
     fn read_cql_frame_header(&mut self, version: u8) -> RCResult<CqlFrameHeader> {
         if version >= 3 {
             let mut header_data = [0; 5];
-            try_rc!(self.take(5).read(&mut header_data), "Error reading response header");
+            try_io!(self.read_exact(&mut header_data), "Error reading response header");
            
             let version_header = header_data[0];
             let flags = header_data[1];
-            let stream = (header_data[2] as u16 + ((header_data[3] as u16) << 8)) as i16;
+            let stream = (((header_data[2] as u16) << 8) + header_data[3] as u16) as i16;
             let opcode = header_data[4];
             Ok(CqlFrameHeader{
                 version: version_header,
@@ -396,11 +815,11 @@ impl<T: std::io::Read> CqlReader for T {
             })
         } else {
             let mut header_data = [0; 4];
-            try_rc!(self.take(4).read(&mut header_data), "Error reading response header");
+            try_io!(self.read_exact(&mut header_data), "Error reading response header");
            
             let version_header = header_data[0];
             let flags = header_data[1];
-            let stream = header_data[2] as i16;
+            let stream = header_data[2] as i8 as i16;
             let opcode = header_data[3];
             Ok(CqlFrameHeader{
                 version: version_header,
@@ -411,30 +830,31 @@ impl<T: std::io::Read> CqlReader for T {
         }
     }
 
-    fn read_cql_response(&mut self, version: u8) -> RCResult<CqlResponse> {
-        let header = try_rc!(self.read_cql_frame_header(version), "Error reading CQL frame header");
-
-        let body_data = try_rc!(self.read_cql_bytes(CqlBytesSize::Cqli32), "Error reading body response");
-        // Code to debug response result. It writes the response's body to a file for inspecting.
-        let path = Path::new("body_data.bin");
-        let display = path.display();
+    fn read_cql_response_body(&mut self, header: CqlFrameHeader, version: u8, compression: Compression,
+                              result_meta: Option<&CqlMetadata>) -> RCResult<CqlResponse> {
+        let mut body_data = try_rc!(self.read_cql_bytes(CqlBytesSize::Cqli32), "Error reading body response");
+        if header.flags & 0x01 != 0 {
+            body_data = try_rc!(decompress(&body_data, compression), "Error decompressing body response");
+        }
+        let mut reader = std::io::BufReader::new(Cursor::new(body_data));
 
-        // Open a file in write-only mode, returns `IoResult<File>`
-        let mut file = match std::fs::File::create(&path) {
-            Err(why) => panic!("couldn't create {}: {}", display, why.description()),
-            Ok(file) => file,
+        let tracing_id = if header.flags & 0x02 != 0 {
+            Some(try_rc!(reader.read_cql_raw_uuid(), "Error reading response tracing id"))
+        } else {
+            None
         };
 
-        match file.write(&body_data) {
-            Err(why) => {
-                panic!("couldn't write to {}: {}", display, why.description())
-            },
-            Ok(_) => println!("successfully wrote to {}", display),
-        }
-
-        //
+        let warnings = if header.flags & 0x08 != 0 {
+            try_rc!(reader.read_cql_string_list(), "Error reading response warnings")
+        } else {
+            vec![]
+        };
 
-        let mut reader = std::io::BufReader::new(Cursor::new(body_data));
+        let custom_payload = if header.flags & 0x04 != 0 {
+            try_rc!(reader.read_cql_bytes_map(), "Error reading response custom payload")
+        } else {
+            CqlBytesMap::new()
+        };
 
         let opcode = opcode_response(header.opcode);
 
@@ -446,7 +866,8 @@ impl<T: std::io::Read> CqlReader for T {
             OpcodeError => {
                 let code = try_bo!(reader.read_u32::<BigEndian>(), "Error reading error code");
                 let msg = try_rc_noption!(reader.read_cql_str(CqlBytesSize::Cqli16), "Error reading error message");
-                ResponseError(code, msg)
+                let error = try_rc!(reader.read_cql_server_error(code, version), "Error reading error body");
+                ResponseError(error, msg)
             },
             OpcodeResult => {
                 let kind = KindResult::from_u32(try_bo!(reader.read_u32::<BigEndian>(), "Error reading result kind"));
@@ -456,27 +877,29 @@ impl<T: std::io::Read> CqlReader for T {
                     },
                     Some(KindRows) => {
                         let collection_size = if version >= 3 { CqlBytesSize::Cqli32 } else { CqlBytesSize::Cqli16 };
-                        ResultRows(try_rc!(reader.read_cql_rows(collection_size), "Error reading result Rows"))
+                        ResultRows(try_rc!(reader.read_cql_rows(collection_size, result_meta), "Error reading result Rows"))
                     },
                     Some(KindSetKeyspace) => {
                         let msg = try_rc_noption!(reader.read_cql_str(CqlBytesSize::Cqli16), "Error reading result Keyspace");
                         ResultKeyspace(msg)
                     },
                     Some(KindSchemaChange) => {
-                        let change  = try_rc_noption!(reader.read_cql_str(CqlBytesSize::Cqli16), "Error reading result SchemaChange (change)");
-                        let keyspace = try_rc_noption!(reader.read_cql_str(CqlBytesSize::Cqli16), "Error reading result SchemaChange (keyspace)");
-                        let table = try_rc_noption!(reader.read_cql_str(CqlBytesSize::Cqli16), "Error reading result SchemaChange (table)");
-                        ResultSchemaChange(change, keyspace, table)
+                        ResultSchemaChange(try_rc!(reader.read_cql_schema_change(version), "Error reading result SchemaChange"))
                     },
                     Some(KindPrepared) => {
                         let id = try_rc!(reader.read_cql_bytes(CqlBytesSize::Cqli16), "Error reading result Prepared (id)");
-                        let metadata = try_rc!(reader.read_cql_metadata(), "Error reading result Prepared (metadata)");
+                        let result_metadata_id = if version >= 0x05 {
+                            Some(try_rc!(reader.read_cql_bytes(CqlBytesSize::Cqli16), "Error reading result Prepared (result metadata id)"))
+                        } else {
+                            None
+                        };
+                        let metadata = try_rc!(reader.read_cql_metadata(version >= 0x04), "Error reading result Prepared (metadata)");
                         let meta_result = if version >= 0x02 { 
-                            Some(try_rc!(reader.read_cql_metadata(), "Error reading result Prepared (metadata result)"))
+                            Some(try_rc!(reader.read_cql_metadata(false), "Error reading result Prepared (metadata result)"))
                         } else {
                             None
                         };
-                        ResultPrepared(CqlPreparedStat { id: id, meta: metadata, meta_result: meta_result})
+                        ResultPrepared(CqlPreparedStat { id: id, result_metadata_id: result_metadata_id, meta: metadata, meta_result: meta_result})
                     }
                     None => return Err(RCError::new("Error reading response body (unknow result kind)", ReadError))
                 }
@@ -487,6 +910,12 @@ impl<T: std::io::Read> CqlReader for T {
             OpcodeAuthSuccess => {
                 ResponseAuthSuccess(try_rc!(reader.read_cql_bytes(CqlBytesSize::Cqli16), "Error reading ResponseAuthSuccess"))
             }
+            OpcodeSupported => {
+                ResponseSupported(try_rc!(reader.read_cql_string_multimap(), "Error reading ResponseSupported"))
+            }
+            OpcodeEvent => {
+                ResponseEvent(try_rc!(reader.read_cql_event(version), "Error reading ResponseEvent"))
+            }
             _ => {
                 ResultUnknown
             },//ResponseEmpty,
@@ -497,6 +926,9 @@ impl<T: std::io::Read> CqlReader for T {
             flags: header.flags,
             stream: header.stream,
             opcode: opcode,
+            tracing_id: tracing_id,
+            warnings: warnings,
+            custom_payload: custom_payload,
             body: body,
         })
     }
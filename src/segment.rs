@@ -0,0 +1,187 @@
+extern crate std;
+extern crate lz4_flex;
+
+use super::def::*;
+use super::def::RCErrorType::*;
+
+// Protocol v5 wraps frames in segments. Each segment carries a CRC24 protected
+// header and a CRC32 protected payload, which is optionally LZ4 compressed.
+
+pub const MAX_SEGMENT_PAYLOAD: usize = (1 << 17) - 1;
+
+const CRC24_INIT: u32 = 0x875060;
+const CRC24_POLY: u32 = 0x1974F0B;
+const CRC32_INITIAL_BYTES: [u8; 4] = [0xFA, 0x2D, 0x55, 0xCA];
+
+const HEADER_LENGTH: usize = 6;
+const HEADER_LENGTH_COMPRESSED: usize = 8;
+const TRAILER_LENGTH: usize = 4;
+
+#[derive(Debug)]
+pub struct Segment {
+    pub payload: Vec<u8>,
+    pub self_contained: bool
+}
+
+///
+/// CRC24 of the first `len` bytes of a little endian segment header.
+///
+pub fn crc24(header: u64, len: usize) -> u32 {
+    let mut crc = CRC24_INIT;
+    let mut bytes = header;
+    for _ in 0 .. len {
+        crc ^= ((bytes & 0xff) as u32) << 16;
+        bytes >>= 8;
+        for _ in 0 .. 8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc & 0xffffff
+}
+
+///
+/// CRC32 of a segment payload. The checksum is seeded with the bytes mandated by the spec.
+///
+pub fn crc32(payload: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for b in CRC32_INITIAL_BYTES.iter().chain(payload.iter()) {
+        crc ^= *b as u32;
+        for _ in 0 .. 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn write_le(buf: &mut Vec<u8>, val: u64, len: usize) {
+    for i in 0 .. len {
+        buf.push((val >> (8 * i)) as u8);
+    }
+}
+
+fn read_le(buf: &[u8], len: usize) -> u64 {
+    buf[.. len].iter().enumerate().fold(0u64, |val, (i, b)| val | (*b as u64) << (8 * i))
+}
+
+fn header_length(compression: Compression) -> usize {
+    match compression {
+        Compression::Lz4 => HEADER_LENGTH_COMPRESSED,
+        _ => HEADER_LENGTH
+    }
+}
+
+pub fn encode_segment(buf: &mut Vec<u8>, payload: &[u8], self_contained: bool, compression: Compression) -> RCResult<()> {
+    if payload.len() > MAX_SEGMENT_PAYLOAD {
+        return Err(RCError::new(format!("Segment payload too large ({} bytes)", payload.len()), SerializeError))
+    }
+    let self_contained_bit = if self_contained { 1u64 } else { 0u64 };
+    match compression {
+        Compression::Lz4 => {
+            let compressed = lz4_flex::block::compress(payload);
+            // Payloads that do not shrink are sent as is, flagged with an uncompressed length of 0
+            let (data, uncompressed_len) = if compressed.len() < payload.len() {
+                (&compressed[..], payload.len())
+            } else {
+                (payload, 0)
+            };
+            let header = data.len() as u64 | ((uncompressed_len as u64) << 17) | (self_contained_bit << 34);
+            write_le(buf, header, 5);
+            write_le(buf, crc24(header, 5) as u64, 3);
+            buf.extend(data);
+            write_le(buf, crc32(data) as u64, TRAILER_LENGTH);
+        },
+        _ => {
+            let header = payload.len() as u64 | (self_contained_bit << 17);
+            write_le(buf, header, 3);
+            write_le(buf, crc24(header, 3) as u64, 3);
+            buf.extend(payload);
+            write_le(buf, crc32(payload) as u64, TRAILER_LENGTH);
+        }
+    }
+    Ok(())
+}
+
+///
+/// Wraps serialized frames in as many segments as needed. Frames that fit in a single segment
+/// are sent self-contained, bigger ones are split across several segments.
+///
+pub fn encode_segments(frames: &[u8], compression: Compression) -> RCResult<Vec<u8>> {
+    let mut buf = Vec::with_capacity(frames.len() + HEADER_LENGTH_COMPRESSED + TRAILER_LENGTH);
+    if frames.len() <= MAX_SEGMENT_PAYLOAD {
+        try_rc!(encode_segment(&mut buf, frames, true, compression), "Error encoding segment");
+    } else {
+        for chunk in frames.chunks(MAX_SEGMENT_PAYLOAD) {
+            try_rc!(encode_segment(&mut buf, chunk, false, compression), "Error encoding segment");
+        }
+    }
+    Ok(buf)
+}
+
+///
+/// Incremental segment decoder: bytes are pushed as they arrive from the wire and complete
+/// segments are returned once their checksums have been verified.
+///
+pub struct SegmentDecoder {
+    compression: Compression,
+    buf: Vec<u8>
+}
+
+impl SegmentDecoder {
+    pub fn new(compression: Compression) -> SegmentDecoder {
+        SegmentDecoder { compression: compression, buf: vec![] }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend(data);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn next_segment(&mut self) -> RCResult<Option<Segment>> {
+        let header_len = header_length(self.compression);
+        if self.buf.len() < header_len {
+            return Ok(None)
+        }
+
+        let header_bytes = header_len - 3;
+        let header = read_le(&self.buf, header_bytes);
+        let header_crc = read_le(&self.buf[header_bytes..], 3) as u32;
+        if crc24(header, header_bytes) != header_crc {
+            return Err(RCError::new("Segment header CRC24 mismatch", ChecksumError))
+        }
+
+        let (payload_len, uncompressed_len, self_contained) = match self.compression {
+            Compression::Lz4 => ((header & 0x1ffff) as usize, ((header >> 17) & 0x1ffff) as usize, header & (1 << 34) != 0),
+            _ => ((header & 0x1ffff) as usize, 0, header & (1 << 17) != 0)
+        };
+
+        let segment_len = header_len + payload_len + TRAILER_LENGTH;
+        if self.buf.len() < segment_len {
+            return Ok(None)
+        }
+
+        let payload_crc = read_le(&self.buf[header_len + payload_len..], TRAILER_LENGTH) as u32;
+        let payload = {
+            let data = &self.buf[header_len .. header_len + payload_len];
+            if crc32(data) != payload_crc {
+                return Err(RCError::new("Segment payload CRC32 mismatch", ChecksumError))
+            }
+            if uncompressed_len > 0 {
+                match lz4_flex::block::decompress(data, uncompressed_len) {
+                    Ok(decompressed) => decompressed,
+                    Err(_) => return Err(RCError::new("Error decompressing segment payload (lz4)", ReadError))
+                }
+            } else {
+                data.to_vec()
+            }
+        };
+        self.buf.drain(.. segment_len);
+
+        Ok(Some(Segment { payload: payload, self_contained: self_contained }))
+    }
+}
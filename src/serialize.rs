@@ -1,11 +1,7 @@
 extern crate std;
 extern crate byteorder;
 
-use std::net::Ipv4Addr;
-use std::net::Ipv6Addr;
-use std::borrow::Cow;
-use self::byteorder::{ReadBytesExt, WriteBytesExt, BigEndian, LittleEndian, Error};
-use std::mem;
+use self::byteorder::{WriteBytesExt, BigEndian};
 
 use super::def::*;
 use super::def::CqlBytesSize::*;
@@ -13,7 +9,6 @@ use super::def::CqlRequestBody::*;
 use super::def::RCErrorType::*;
 use super::def::Query::*;
 use super::def::CqlValue::*;
-use super::client::Client;
 
 pub trait CqlSerializable<'a> {
     fn len(&'a self, version: u8) -> usize;
@@ -31,7 +26,7 @@ macro_rules! write_size(
 );
 
 impl<'a> CqlSerializable<'a> for CqlPair {
-    fn serialize_size<T: std::io::Write>(&'a self, buf: &mut T, bytes_size: CqlBytesSize, version: u8) -> RCResult<()> {
+    fn serialize_size<T: std::io::Write>(&'a self, buf: &mut T, _bytes_size: CqlBytesSize, _version: u8) -> RCResult<()> {
         try_bo!(buf.write_u16::<BigEndian>(self.key.len() as u16), "Error serializing CqlPair (key length)");
         try_io!(buf.write(self.key.as_bytes()), "Error serializing CqlPair (key)");
         try_bo!(buf.write_u16::<BigEndian>(self.value.len() as u16), "Error serializing CqlPair (value length)");
@@ -43,17 +38,17 @@ impl<'a> CqlSerializable<'a> for CqlPair {
     }
 
 
-    fn len(&'a self, version: u8) -> usize {
+    fn len(&'a self, _version: u8) -> usize {
         return 4 + self.key.len() + self.value.len();
     }
 }
 
 
 impl<'a> CqlSerializable<'a> for CqlStringMap {
-    fn serialize_size<T: std::io::Write>(&'a self, buf: &mut T, bytes_size: CqlBytesSize, version: u8) -> RCResult<()> {
+    fn serialize_size<T: std::io::Write>(&'a self, buf: &mut T, _bytes_size: CqlBytesSize, version: u8) -> RCResult<()> {
         try_bo!(buf.write_u16::<BigEndian>(self.pairs.len() as u16), "Error serializing CqlStringMap (length)");
         for pair in self.pairs.iter() {
-            pair.serialize_size(buf, Cqli16, version);
+            try_rc!(pair.serialize_size(buf, Cqli16, version), "Error serializing CqlStringMap (pair)");
         }
         Ok(())
     }
@@ -84,65 +79,234 @@ fn serialize_header<T: std::io::Write>(buf: &mut T, version: &u8, flags: &u8, st
     Ok(())
 }
 
+// The stream is a [byte] up to v2 and a [short] from v3 on
+fn header_len(version: u8) -> usize {
+    if version >= 3 { 9 } else { 8 }
+}
+
+// Query flags are a [byte] up to v4 and an [int] from v5 on
+fn query_flags_len(version: u8) -> usize {
+    if version >= 5 { 4 } else { 1 }
+}
+
+fn serialize_query_flags<T: std::io::Write>(buf: &mut T, flags: u8, version: u8) -> RCResult<()> {
+    if version >= 5 {
+        try_bo!(buf.write_u32::<BigEndian>(flags as u32), "Error serializing query flags");
+    } else {
+        try_bo!(buf.write_u8(flags), "Error serializing query flags");
+    }
+    Ok(())
+}
+
+// Paging and the query flags were introduced in v2, v1 only knows about the consistency
+impl<'a> CqlSerializable<'a> for QueryParams<'a> {
+    fn serialize_size<T: std::io::Write>(&'a self, buf: &mut T, _bytes_size: CqlBytesSize, version: u8) -> RCResult<()> {
+        self.serialize(buf, version)
+    }
+
+    fn serialize<T: std::io::Write>(&'a self, buf: &mut T, version: u8) -> RCResult<()> {
+        try_bo!(buf.write_u16::<BigEndian>(self.consistency as u16), "Error serializing query parameters (consistency)");
+        if version < 2 {
+            return Ok(())
+        }
+        let flags = self.flags();
+        if flags & 0x20 != 0 && version < 3 {
+            return Err(RCError::new("Default timestamps need protocol v3 or later", SerializeError))
+        }
+        if flags & 0x80 != 0 && version < 5 {
+            return Err(RCError::new("Per request keyspaces need protocol v5 or later", SerializeError))
+        }
+        if flags & 0x40 != 0 {
+            if version < 3 {
+                return Err(RCError::new("Named values need protocol v3 or later", SerializeError))
+            }
+            if !self.values.is_empty() {
+                return Err(RCError::new("Positional and named values cannot be mixed", SerializeError))
+            }
+        }
+        try_rc!(serialize_query_flags(buf, flags, version), "Error serializing query parameters (flags)");
+        if flags & 0x40 != 0 {
+            try_bo!(buf.write_i16::<BigEndian>(self.named_values.len() as i16), "Error serializing query parameters (values length)");
+            for &(name, ref v) in self.named_values.iter() {
+                write_size!(buf, name.len(), Cqli16);
+                try_io!(buf.write(name.as_bytes()), "Error serializing query parameters (value name)");
+                try_rc!(v.serialize_size(buf, Cqli32, version), "Error serializing query parameters (value)");
+            }
+        } else if flags & 0x01 != 0 {
+            try_bo!(buf.write_i16::<BigEndian>(self.values.len() as i16), "Error serializing query parameters (values length)");
+            for v in self.values.iter() {
+                try_rc!(v.serialize_size(buf, Cqli32, version), "Error serializing query parameters (value)");
+            }
+        }
+        if let Some(page_size) = self.page_size {
+            try_bo!(buf.write_i32::<BigEndian>(page_size), "Error serializing query parameters (page size)");
+        }
+        if let Some(ref paging_state) = self.paging_state {
+            write_size!(buf, paging_state.len(), Cqli32);
+            try_io!(buf.write(paging_state), "Error serializing query parameters (paging state)");
+        }
+        if let Some(serial_consistency) = self.serial_consistency {
+            try_bo!(buf.write_u16::<BigEndian>(serial_consistency as u16), "Error serializing query parameters (serial consistency)");
+        }
+        if let Some(timestamp) = self.timestamp {
+            try_bo!(buf.write_i64::<BigEndian>(timestamp), "Error serializing query parameters (timestamp)");
+        }
+        if let Some(ref keyspace) = self.keyspace {
+            write_size!(buf, keyspace.len(), Cqli16);
+            try_io!(buf.write(keyspace.as_bytes()), "Error serializing query parameters (keyspace)");
+        }
+        Ok(())
+    }
+
+    fn len(&'a self, version: u8) -> usize {
+        if version < 2 {
+            return 2
+        }
+        let mut len = 2 + query_flags_len(version);
+        if !self.named_values.is_empty() {
+            len += self.named_values.iter().fold(2, |a, &(name, ref v)| a + 2 + name.len() + 4 + v.len(version));
+        } else if !self.values.is_empty() {
+            len += self.values.iter().fold(2, |a, b| a + 4 + b.len(version));
+        }
+        if self.page_size.is_some() {
+            len += 4;
+        }
+        if let Some(ref paging_state) = self.paging_state {
+            len += 4 + paging_state.len();
+        }
+        if self.serial_consistency.is_some() {
+            len += 2;
+        }
+        if self.timestamp.is_some() {
+            len += 8;
+        }
+        if let Some(ref keyspace) = self.keyspace {
+            len += 2 + keyspace.len();
+        }
+        len
+    }
+}
+
+impl<'a> CqlSerializable<'a> for BatchParams {
+    fn serialize_size<T: std::io::Write>(&'a self, buf: &mut T, _bytes_size: CqlBytesSize, version: u8) -> RCResult<()> {
+        self.serialize(buf, version)
+    }
+
+    fn serialize<T: std::io::Write>(&'a self, buf: &mut T, version: u8) -> RCResult<()> {
+        try_bo!(buf.write_u16::<BigEndian>(self.consistency as u16), "Error serializing batch parameters (consistency)");
+        if version < 3 {
+            return Ok(())
+        }
+        if self.keyspace.is_some() && version < 5 {
+            return Err(RCError::new("Per request keyspaces need protocol v5 or later", SerializeError))
+        }
+        try_rc!(serialize_query_flags(buf, self.flags(), version), "Error serializing batch parameters (flags)");
+        if let Some(serial_consistency) = self.serial_consistency {
+            try_bo!(buf.write_u16::<BigEndian>(serial_consistency as u16), "Error serializing batch parameters (serial consistency)");
+        }
+        if let Some(timestamp) = self.timestamp {
+            try_bo!(buf.write_i64::<BigEndian>(timestamp), "Error serializing batch parameters (timestamp)");
+        }
+        if let Some(ref keyspace) = self.keyspace {
+            write_size!(buf, keyspace.len(), Cqli16);
+            try_io!(buf.write(keyspace.as_bytes()), "Error serializing batch parameters (keyspace)");
+        }
+        Ok(())
+    }
+
+    fn len(&'a self, version: u8) -> usize {
+        if version < 3 {
+            return 2
+        }
+        let mut len = 2 + query_flags_len(version);
+        if self.serial_consistency.is_some() {
+            len += 2;
+        }
+        if self.timestamp.is_some() {
+            len += 8;
+        }
+        if let Some(ref keyspace) = self.keyspace {
+            len += 2 + keyspace.len();
+        }
+        len
+    }
+}
+
 impl<'a> CqlSerializable<'a> for CqlRequest<'a> {
-    fn serialize_size<T: std::io::Write>(&'a self, buf: &mut T, bytes_size: CqlBytesSize, version: u8) -> RCResult<()> {
+    fn serialize_size<T: std::io::Write>(&'a self, _buf: &mut T, _bytes_size: CqlBytesSize, _version: u8) -> RCResult<()> {
         Err(RCError::new("Cannot serialize Request without Client context", WriteError))
     }
 
     fn serialize<T: std::io::Write>(&'a self, buf: &mut T, version: u8) -> RCResult<()> {
-        let len = (self.len(version)-8) as u32;
+        let len = (self.len(version) - header_len(version)) as u32;
         let ocode = self.opcode as u8;
-        serialize_header(buf, &version, &self.flags, &self.stream, &ocode, &len);
+        try_rc!(serialize_header(buf, &version, &self.flags, &self.stream, &ocode, &len), "Error serializing CqlRequest (header)");
         
         match self.body {
-            RequestExec(ref preps, ref params, ref cons, flags) => {
-                try_bo!(buf.write_i16::<BigEndian>(preps.len() as i16), "Error serializing EXEC request (id length)");
-                try_io!(buf.write(&preps), "Error serializing EXEC request (id)");
+            RequestExec(preps, ref params) => {
+                try_bo!(buf.write_i16::<BigEndian>(preps.id.len() as i16), "Error serializing EXEC request (id length)");
+                try_io!(buf.write(&preps.id), "Error serializing EXEC request (id)");
+                if version >= 5 {
+                    let result_metadata_id = match preps.result_metadata_id {
+                        Some(ref id) => id,
+                        None => return Err(RCError::new("Prepared statement has no result metadata id", SerializeError))
+                    };
+                    try_bo!(buf.write_i16::<BigEndian>(result_metadata_id.len() as i16), "Error serializing EXEC request (result metadata id length)");
+                    try_io!(buf.write(result_metadata_id), "Error serializing EXEC request (result metadata id)");
+                }
                 if version >= 2 {
-                    try_bo!(buf.write_u16::<BigEndian>(*cons as u16), "Error serializing CqlRequest (query consistency)");
-                    try_bo!(buf.write_u8(flags), "Error serializing CqlRequest (query flags)");
-
-                    try_bo!(buf.write_i16::<BigEndian>(params.len() as i16), "Error serializing EXEC request (params length)");                
-                    for v in params.iter() {
-                        v.serialize_size(buf, Cqli32, version);
-                    }
+                    try_rc!(params.serialize(buf, version), "Error serializing EXEC request (parameters)");
                 } else {
-                    try_bo!(buf.write_i16::<BigEndian>(params.len() as i16), "Error serializing EXEC request (params length)");                
-                    for v in params.iter() {
-                        v.serialize_size(buf, Cqli32, version);
+                    if !params.named_values.is_empty() {
+                        return Err(RCError::new("Named values need protocol v3 or later", SerializeError))
+                    }
+                    try_bo!(buf.write_i16::<BigEndian>(params.values.len() as i16), "Error serializing EXEC request (params length)");                
+                    for v in params.values.iter() {
+                        try_rc!(v.serialize_size(buf, Cqli32, version), "Error serializing EXEC request (value)");
                     }
-                    try_bo!(buf.write_u16::<BigEndian>(*cons as u16), "Error serializing CqlRequest (query consistency)");
+                    try_bo!(buf.write_u16::<BigEndian>(params.consistency as u16), "Error serializing CqlRequest (query consistency)");
                 }
                 Ok(())
             },
-            RequestBatch(ref q_vec, ref r_type, ref con, flags) => {
+            RequestBatch(ref q_vec, ref r_type, ref params) => {
                 try_bo!(buf.write_u8(*r_type as u8), "Error serializing BATCH request (request type)");
                 try_bo!(buf.write_u16::<BigEndian>(q_vec.len() as u16), "Error serializing BATCH request (number of requests)");
-                q_vec.iter().all(|r| { r.serialize(buf, version); true });
-                try_bo!(buf.write_u16::<BigEndian>(*con as u16), "Error serializing BATCH request (consistency)");
-                if version >= 3 {
-                    try_bo!(buf.write_u8(0 as u8), "Error serializing BATCH request (flags)");
+                for r in q_vec.iter() {
+                    try_rc!(r.serialize(buf, version), "Error serializing BATCH request (query)");
                 }
+                try_rc!(params.serialize(buf, version), "Error serializing BATCH request (parameters)");
                 Ok(())
             },
             RequestStartup(ref map) => {
                 map.serialize(buf, version)
             },
-            RequestQuery(ref query_str, ref consistency, flags) => {
+            RequestQuery(query_str, ref params) => {
+                if version < 2 && params.flags() & 0x01 != 0 {
+                    return Err(RCError::new("Bind values for queries need protocol v2 or later", SerializeError))
+                }
                 let len_str = query_str.len() as u32;
                 try_bo!(buf.write_u32::<BigEndian>(len_str), "Error serializing CqlRequest (query length)");
                 try_io!(buf.write(query_str.as_bytes()), "Error serializing CqlRequest (query)");
-                try_bo!(buf.write_u16::<BigEndian>(*consistency as u16), "Error serializing CqlRequest (query consistency)");
-                if version >= 2 {
-                    try_bo!(buf.write_u8(flags), "Error serializing CqlRequest (query flags)");
-                }
+                try_rc!(params.serialize(buf, version), "Error serializing CqlRequest (query parameters)");
                 Ok(())
             },
-            RequestPrepare(ref query_str) => {
+            RequestPrepare(query_str) => {
                 let len_str = query_str.len() as u32;
                 try_bo!(buf.write_u32::<BigEndian>(len_str), "Error serializing CqlRequest (query length)");
                 try_io!(buf.write(query_str.as_bytes()), "Error serializing CqlRequest (query)");
-                Ok(())               
+                if version >= 5 {
+                    try_bo!(buf.write_u32::<BigEndian>(0), "Error serializing CqlRequest (prepare flags)");
+                }
+                Ok(())
+            },
+            RequestRegister(events) => {
+                try_bo!(buf.write_u16::<BigEndian>(events.len() as u16), "Error serializing REGISTER request (events length)");
+                for event in events.iter() {
+                    let event_str = event.as_str();
+                    try_bo!(buf.write_u16::<BigEndian>(event_str.len() as u16), "Error serializing REGISTER request (event length)");
+                    try_io!(buf.write(event_str.as_bytes()), "Error serializing REGISTER request (event)");
+                }
+                Ok(())
             },
             RequestAuthResponse(ref token) => {
                 let len_str = token.len() as u32;
@@ -155,25 +319,33 @@ impl<'a> CqlSerializable<'a> for CqlRequest<'a> {
     }
 
     fn len(&'a self, version: u8) -> usize {
-        8 + match self.body {
+        header_len(version) + match self.body {
             RequestStartup(ref map) => map.len(version),
-            RequestQuery(ref query_str, _, _) => {
-                let final_bytes = if version >= 2 { 3 } else { 2 };
-                4 + query_str.len() + final_bytes
+            RequestQuery(query_str, ref params) => {
+                4 + query_str.len() + params.len(version)
             },
-            RequestPrepare(ref query_str) => 4 + query_str.len(),
-            RequestExec(ref preps, ref values, _, _) => {
-                let final_bytes = if version >= 2 { 3 } else { 2 };
-                let values_size:usize = values.iter().fold(0, |a, ref b| a + 4 + b.len(version));
-                2 + preps.len() as usize + 2 +  values_size + final_bytes as usize
+            RequestPrepare(query_str) => {
+                let final_bytes = if version >= 5 { 4 } else { 0 };
+                4 + query_str.len() + final_bytes
             },
-            RequestBatch(ref q_vec, ref r_type, ref con, flags) => {
-                let q_vec_size:usize = q_vec.iter().fold(0, |a, ref b| a + b.len(version));
-                if version >= 3 {
-                    3 + q_vec_size + 3
+            RequestExec(preps, ref params) => {
+                let params_size = if version >= 2 {
+                    params.len(version)
                 } else {
-                    3 + q_vec_size + 2
-                }
+                    params.values.iter().fold(4, |a, b| a + 4 + b.len(version))
+                };
+                let result_metadata_id_size = match preps.result_metadata_id {
+                    Some(ref id) if version >= 5 => 2 + id.len(),
+                    _ => 0
+                };
+                2 + preps.id.len() + result_metadata_id_size + params_size
+            },
+            RequestBatch(ref q_vec, _r_type, ref params) => {
+                let q_vec_size:usize = q_vec.iter().fold(0, |a, b| a + b.len(version));
+                3 + q_vec_size + params.len(version)
+            },
+            RequestRegister(events) => {
+                events.iter().fold(2, |a, e| a + 2 + e.as_str().len())
             },
             RequestAuthResponse(ref token) => {
                 4 + token.len()
@@ -183,27 +355,39 @@ impl<'a> CqlSerializable<'a> for CqlRequest<'a> {
     }
 }
 
+fn serialize_batch_values<T: std::io::Write>(buf: &mut T, values: &[CqlValue], version: u8) -> RCResult<()> {
+    try_bo!(buf.write_u16::<BigEndian>(values.len() as u16), "Error serializing BATCH query (values length)");
+    for v in values.iter() {
+        try_rc!(v.serialize(buf, version), "Error serializing BATCH query (value)");
+    }
+    Ok(())
+}
+
+fn batch_values_len(values: &[CqlValue], version: u8) -> usize {
+    values.iter().fold(2, |a, b| a + 4 + b.len(version))
+}
+
 impl<'a> CqlSerializable<'a> for Query {
-    fn serialize_size<T: std::io::Write>(&'a self, buf: &mut T, bytes_size: CqlBytesSize, version: u8) -> RCResult<()> {
+    fn serialize_size<T: std::io::Write>(&'a self, buf: &mut T, _bytes_size: CqlBytesSize, version: u8) -> RCResult<()> {
         self.serialize(buf, version)
     }
 
     fn serialize<T: std::io::Write>(&'a self, buf: &mut T, version: u8) -> RCResult<()> {
         match *self {
-            QueryStr(ref q_str) => {
+            QueryStr(ref q_str) | QueryStrValues(ref q_str, _) => {
                 try_bo!(buf.write_u8(0u8), "Error serializing BATCH query (type)");
                 write_size!(buf, q_str.len(), Cqli32);
                 try_io!(buf.write(q_str.as_bytes()), "Error serializing BATCH query (query string)");
-                try_bo!(buf.write_u16::<BigEndian>(0u16), "Error serializing BATCH query (values length)");
-                Ok(())
+                match *self {
+                    QueryStrValues(_, ref values) => serialize_batch_values(buf, values, version),
+                    _ => serialize_batch_values(buf, &[], version)
+                }
             },
             QueryPrepared(ref preps, ref values) => {
                 try_bo!(buf.write_u8(1u8), "Error serializing BATCH prepared query (type)");
                 write_size!(buf, preps.len(), Cqli16);
-                try_io!(buf.write(&preps), "Error serializing BATCH prepared query (id)");
-                try_bo!(buf.write_u16::<BigEndian>(values.len() as u16), "Error serializing BATCH prepared query (values length)");
-                values.iter().all(|v| { v.serialize(buf, version); true});
-                Ok(())
+                try_io!(buf.write(preps), "Error serializing BATCH prepared query (id)");
+                serialize_batch_values(buf, values, version)
             },
             _ => Err(RCError::new(" ad serialize query in BATH request", WriteError))
         }
@@ -214,15 +398,47 @@ impl<'a> CqlSerializable<'a> for Query {
             QueryStr(ref q_str) => {
                 7 + q_str.len()
             },
+            QueryStrValues(ref q_str, ref values) => {
+                5 + q_str.len() + batch_values_len(values, version)
+            },
             QueryPrepared(ref preps, ref values) => {
-                let values_size:usize = values.iter().fold(0, |a, ref b| a + 4 + b.len(version));
-                5 + preps.len() + values_size
+                3 + preps.len() + batch_values_len(values, version)
             },
             _ => 0
         }
     }
 }
 
+// Before v3 the number of elements of a collection and their lengths are [short], then they are [int]
+fn collection_elem_size(version: u8) -> CqlBytesSize {
+    if version >= 3 { Cqli32 } else { Cqli16 }
+}
+
+fn collection_prefix_len(version: u8) -> usize {
+    if version >= 3 { 4 } else { 2 }
+}
+
+///
+/// Zigzag encoded variable length integer, as used by the `duration` type, with as many leading
+/// 1 bits in the first byte as extra bytes follow.
+///
+pub fn encode_vint(value: i64) -> Vec<u8> {
+    let v = ((value << 1) ^ (value >> 63)) as u64;
+    let size = ((639 - (v | 1).leading_zeros() * 9) >> 6) as usize;
+    if size == 9 {
+        let mut vec = vec![0xffu8];
+        vec.extend((0 .. 8).rev().map(|i| (v >> (8 * i)) as u8));
+        return vec
+    }
+    let mut vec: Vec<u8> = (0 .. size).rev().map(|i| (v >> (8 * i)) as u8).collect();
+    vec[0] |= !(0xffu8 >> (size - 1));
+    vec
+}
+
+fn duration_len(d: &Duration) -> usize {
+    encode_vint(d.months as i64).len() + encode_vint(d.days as i64).len() + encode_vint(d.nanoseconds).len()
+}
+
 impl<'a> CqlSerializable<'a> for CqlValue {
     fn serialize_size<T: std::io::Write>(&'a self, buf: &mut T, bytes_size: CqlBytesSize, version: u8) -> RCResult<()> {
         match *self {
@@ -232,7 +448,10 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                     try_io!(buf.write(s.as_bytes()), "Error serializing CqlValue (ascci)");
                     Ok(())
                 }
-                None => Ok(())
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlBigInt(ref o) => match *o {
                 Some(ref i) => {
@@ -240,15 +459,21 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                     try_bo!(buf.write_i64::<BigEndian>(*i), "Error serializing CqlValue (Bigint)");
                     Ok(())
                 }
-                None => Ok(())                
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlBlob(ref o) => match *o {
                 Some(ref b) => {
                     write_size!(buf, b.len(), bytes_size);
-                    try_io!(buf.write(&b), "Error serializing CqlValue (Blob)");
+                    try_io!(buf.write(b), "Error serializing CqlValue (Blob)");
+                    Ok(())
+                }
+                None => {
+                    write_size!(buf, -1, bytes_size);
                     Ok(())
                 }
-                None => Ok(())                
             },
             CqlBoolean(ref o) => match *o {
                 Some(ref b) => {
@@ -256,7 +481,10 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                     try_bo!(buf.write_u8(*b as u8), "Error serializing CqlValue (Boolean)");
                     Ok(())
                 }
-                None => Ok(())                
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlCounter(ref o) => match *o {
                 Some(ref c) => {
@@ -264,16 +492,61 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                     try_bo!(buf.write_i64::<BigEndian>(*c), "Error serializing CqlValue (Counter)");
                     Ok(())
                 }
-                None => Ok(())                
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
+            },
+            CqlDate(ref o) => match *o {
+                Some(ref v) => {
+                    write_size!(buf, 4, bytes_size);
+                    try_bo!(buf.write_u32::<BigEndian>(*v), "Error serializing CqlValue (Date)");
+                    Ok(())
+                }
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
+            },
+            CqlDecimal(ref o) => match *o {
+                Some(ref d) => {
+                    let unscaled = d.unscaled.to_signed_bytes_be();
+                    write_size!(buf, 4 + unscaled.len(), bytes_size);
+                    try_bo!(buf.write_i32::<BigEndian>(d.scale), "Error serializing CqlValue (Decimal)");
+                    try_io!(buf.write(&unscaled), "Error serializing CqlValue (Decimal)");
+                    Ok(())
+                }
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
-            CqlDecimal(_) => Err(RCError::new("Decimal seralization not implemented", SerializeError)),
             CqlDouble(ref o) => match *o {
                 Some(ref d) => {
                     write_size!(buf, 8, bytes_size);
                     try_bo!(buf.write_f64::<BigEndian>(*d), "Error serializing CqlValue (Double)");
                     Ok(())
                 }
-                None => Ok(())                
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
+            },
+            CqlDuration(ref o) => match *o {
+                Some(ref d) => {
+                    if !d.is_valid() {
+                        return Err(RCError::new(format!("Error serializing CqlValue (Duration with mixed signs: {:?})", d), SerializeError))
+                    }
+                    write_size!(buf, duration_len(d), bytes_size);
+                    for v in [d.months as i64, d.days as i64, d.nanoseconds].iter() {
+                        try_io!(buf.write(&encode_vint(*v)), "Error serializing CqlValue (Duration)");
+                    }
+                    Ok(())
+                }
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlFloat(ref o) => match *o {
                 Some(ref f) => {
@@ -281,7 +554,10 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                     try_bo!(buf.write_f32::<BigEndian>(*f), "Error serializing CqlValue (Float)");
                     Ok(())
                 }
-                None => Ok(())                
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlInet(ref o) => match *o {
                 Some(ref ip) => match *ip {
@@ -300,7 +576,10 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                         Ok(())
                     },
                 },
-                None => Ok(())
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlInt(ref o) => match *o {
                 Some(ref i) => {
@@ -308,46 +587,66 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                     try_bo!(buf.write_i32::<BigEndian>(*i), "Error serializing CqlValue (Int)");
                     Ok(())
                 }
-                None => Ok(())                
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlList(ref o) => match *o {
                 Some(ref v) => {
-                    let len = v.len();
-                    if version >= 3 {
-                        try_bo!(buf.write_i32::<BigEndian>(len as i32), "Error serializing CqlValue (List length)")
-                    } else {
-                        try_bo!(buf.write_i16::<BigEndian>(len as i16), "Error serializing CqlValue (List length)")
+                    write_size!(buf, self.len(version), bytes_size);
+                    let elem_size = collection_elem_size(version);
+                    write_size!(buf, v.len(), elem_size);
+                    for e in v.iter() {
+                        try_rc!(e.serialize_size(buf, elem_size, version), "Error serializing CqlValue (List element)");
                     }
-                    v.iter().map(|e| e.serialize_size(buf, Cqli16, version));
                     Ok(())
                 },
-                None => Ok(())
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlMap(ref o) => match *o {
                 Some(ref v) => {
-                    let len = v.len();
-                    if version >= 3 {
-                        try_bo!(buf.write_i32::<BigEndian>(len as i32), "Error serializing CqlValue (List length)")
-                    } else {
-                        try_bo!(buf.write_i16::<BigEndian>(len as i16), "Error serializing CqlValue (List length)")
+                    write_size!(buf, self.len(version), bytes_size);
+                    let elem_size = collection_elem_size(version);
+                    write_size!(buf, v.len(), elem_size);
+                    for e in v.iter() {
+                        try_rc!(e.serialize_size(buf, elem_size, version), "Error serializing CqlValue (Map element)");
                     }
-                    v.iter().map(|e| e.serialize_size(buf, Cqli16, version));
                     Ok(())
                 },
-                None => Ok(())
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlSet(ref o) => match *o {
                 Some(ref v) => {
-                    let len = v.len();
-                    if version >= 3 {
-                        try_bo!(buf.write_i32::<BigEndian>(len as i32), "Error serializing CqlValue (List length)")
-                    } else {
-                        try_bo!(buf.write_i16::<BigEndian>(len as i16), "Error serializing CqlValue (List length)")
+                    write_size!(buf, self.len(version), bytes_size);
+                    let elem_size = collection_elem_size(version);
+                    write_size!(buf, v.len(), elem_size);
+                    for e in v.iter() {
+                        try_rc!(e.serialize_size(buf, elem_size, version), "Error serializing CqlValue (Set element)");
                     }
-                    v.iter().map(|e| e.serialize_size(buf, Cqli16, version));
                     Ok(())
                 },
-                None => Ok(())
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
+            },
+            CqlSmallInt(ref o) => match *o {
+                Some(ref v) => {
+                    write_size!(buf, 2, bytes_size);
+                    try_bo!(buf.write_i16::<BigEndian>(*v), "Error serializing CqlValue (SmallInt)");
+                    Ok(())
+                }
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlText(ref o) => match *o {
                 Some(ref s) => {
@@ -355,7 +654,21 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                     try_io!(buf.write(s.as_bytes()), "Error serializing CqlValue (Text)");
                     Ok(())
                 }
-                None => Ok(())
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
+            },
+            CqlTime(ref o) => match *o {
+                Some(ref v) => {
+                    write_size!(buf, 8, bytes_size);
+                    try_bo!(buf.write_i64::<BigEndian>(*v), "Error serializing CqlValue (Time)");
+                    Ok(())
+                }
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlTimestamp(ref o) => match *o {
                 Some(ref i) => {
@@ -363,7 +676,48 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                     try_bo!(buf.write_u64::<BigEndian>(*i), "Error serializing CqlValue (Counter)");
                     Ok(())
                 }
-                None => Ok(())                
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
+            },
+            CqlTinyInt(ref o) => match *o {
+                Some(ref v) => {
+                    write_size!(buf, 1, bytes_size);
+                    try_bo!(buf.write_i8(*v), "Error serializing CqlValue (TinyInt)");
+                    Ok(())
+                }
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
+            },
+            CqlTuple(ref o) => match *o {
+                Some(ref components) => {
+                    write_size!(buf, self.len(version), bytes_size);
+                    for v in components.iter() {
+                        try_rc!(v.serialize_size(buf, Cqli32, version), "Error serializing CqlValue (tuple component)");
+                    }
+                    Ok(())
+                }
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
+            },
+            CqlUdt(ref o) => match *o {
+                Some(ref fields) => {
+                    write_size!(buf, self.len(version), bytes_size);
+                    // Positional, the field names are not part of the value
+                    for &(_, ref v) in fields.iter() {
+                        try_rc!(v.serialize_size(buf, Cqli32, version), "Error serializing CqlValue (UDT field)");
+                    }
+                    Ok(())
+                }
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlUuid(ref o) => match *o {
                 Some(ref u) => {
@@ -371,7 +725,10 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                     try_io!(buf.write(u.as_bytes()), "Error serializing CqlValue (Uuid)");
                     Ok(())
                 }
-                None => Ok(())                
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlTimeUuid(ref o) => match *o {
                 Some(ref u) => {
@@ -379,7 +736,10 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                     try_io!(buf.write(u.as_bytes()), "Error serializing CqlValue (TimeUuid)");
                     Ok(())
                 }
-                None => Ok(())                
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
             },
             CqlVarchar(ref o) => match *o {
                 Some(ref s) => {
@@ -387,9 +747,30 @@ impl<'a> CqlSerializable<'a> for CqlValue {
                     try_io!(buf.write(s.as_bytes()), "Error serializing CqlValue (Varchar)");
                     Ok(())
                 }
-                None => Ok(())
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
+            },
+            CqlVarint(ref o) => match *o {
+                Some(ref v) => {
+                    let bytes = v.to_signed_bytes_be();
+                    write_size!(buf, bytes.len(), bytes_size);
+                    try_io!(buf.write(&bytes), "Error serializing CqlValue (Varint)");
+                    Ok(())
+                }
+                None => {
+                    write_size!(buf, -1, bytes_size);
+                    Ok(())
+                }
+            },
+            CqlUnset => {
+                if version < 4 {
+                    return Err(RCError::new("Unset values are only supported from protocol v4", SerializeError))
+                }
+                write_size!(buf, -2, bytes_size);
+                Ok(())
             },
-            CqlVarint(_) => Err(RCError::new("Varint seralization not implemented", SerializeError)),
             _ => Err(RCError::new("Error serializing CqlValue (no", SerializeError))
         }
 
@@ -400,94 +781,125 @@ impl<'a> CqlSerializable<'a> for CqlValue {
     }
 
     fn len(&'a self, version: u8) -> usize {
-        match self {
-            &CqlASCII(ref o) => match *o {
-                Some(ref s) => s.len() as usize,
+        match *self {
+            CqlASCII(ref o) => match *o {
+                Some(ref s) => s.len(),
                 None => 0
             },
-            &CqlBigInt(ref o) => match *o {
+            CqlBigInt(ref o) => match *o {
                 Some(_) => std::mem::size_of::<i64>(),
                 None => 0     
             },
-            &CqlBlob(ref o) => match *o {
-                Some(ref b) => b.len() as usize,
+            CqlBlob(ref o) => match *o {
+                Some(ref b) => b.len(),
                 None => 0               
             },
-            &CqlBoolean(ref o) => match *o {
+            CqlBoolean(ref o) => match *o {
                 Some(_) => std::mem::size_of::<u8>(),
                 None => 0                
             },
-            &CqlCounter(ref o) => match *o {
+            CqlCounter(ref o) => match *o {
                 Some(_) => std::mem::size_of::<i64>(),
                 None => 0     
             },
-            &CqlDecimal(_) => 0,
-            &CqlDouble(ref o) => match *o {
+            CqlDate(ref o) => match *o {
+                Some(_) => std::mem::size_of::<u32>(),
+                None => 0     
+            },
+            CqlDecimal(ref o) => match *o {
+                Some(ref d) => 4 + d.unscaled.to_signed_bytes_be().len(),
+                None => 0
+            },
+            CqlDouble(ref o) => match *o {
                 Some(_) => std::mem::size_of::<f64>(),
                 None => 0     
             },
-            &CqlFloat(ref o) => match *o {
+            CqlDuration(ref o) => match *o {
+                Some(ref d) => duration_len(d),
+                None => 0
+            },
+            CqlFloat(ref o) => match *o {
                 Some(_) => std::mem::size_of::<f32>(),
                 None => 0     
             },
-            &CqlInet(ref o) => match *o {
+            CqlInet(ref o) => match *o {
                 Some(ref ip) => match *ip {
                     IpAddr::Ipv4(_) => 5,
                     IpAddr::Ipv6(_) => 17
                 },
                 None => 0
             },
-            &CqlInt(ref o) => match *o {
+            CqlInt(ref o) => match *o {
                 Some(_) => std::mem::size_of::<i32>(),
                 None => 0     
             },
-            &CqlList(ref o) => match *o {
+            CqlList(ref o) => match *o {
                 Some(ref v) => {
-                    if v.len() == 0 { 0 }
-                    else {
-                        // Lists contain [short bytes] elements, hence the 2
-                        v.len() * (2 + v[0].len(version))
-                    }                   
+                    let prefix = collection_prefix_len(version);
+                    v.iter().fold(prefix, |a, e| a + prefix + e.len(version))
                 },
                 None => 0
             },
-            &CqlMap(ref o) => match *o {
+            CqlMap(ref o) => match *o {
                 Some(ref v) => {
-                    if v.len() == 0 { 0 }
-                    else {
-                        // Maps contain [short bytes] elements, hence the 2
-                        v.len() * (2 + v[0].len(version))
-                    }                   
+                    let prefix = collection_prefix_len(version);
+                    v.iter().fold(prefix, |a, e| a + 2 * prefix + e.key.len(version) + e.value.len(version))
                 },
                 None => 0
             },
-            &CqlSet(ref o) => match *o {
+            CqlSet(ref o) => match *o {
                 Some(ref v) => {
-                    if v.len() == 0 { 0 }
-                    else {
-                        // Sets contain [short bytes] elements, hence the 2
-                        v.len() * (2 + v[0].len(version))
-                    }                   
+                    let prefix = collection_prefix_len(version);
+                    v.iter().fold(prefix, |a, e| a + prefix + e.len(version))
                 },
                 None => 0
             },
-            &CqlTimestamp(ref o) => match *o {
+            CqlSmallInt(ref o) => match *o {
+                Some(_) => std::mem::size_of::<i16>(),
+                None => 0     
+            },
+            CqlTime(ref o) => match *o {
+                Some(_) => std::mem::size_of::<i64>(),
+                None => 0     
+            },
+            CqlTimestamp(ref o) => match *o {
                 Some(_) => std::mem::size_of::<u64>(),
                 None => 0     
             },
-            &CqlUuid(ref o) => match *o {
+            CqlTinyInt(ref o) => match *o {
+                Some(_) => std::mem::size_of::<i8>(),
+                None => 0     
+            },
+            CqlTuple(ref o) => match *o {
+                // Components are [bytes], hence the 4
+                Some(ref components) => components.iter().fold(0, |a, v| a + 4 + v.len(version)),
+                None => 0
+            },
+            CqlUdt(ref o) => match *o {
+                // Fields are [bytes], hence the 4
+                Some(ref fields) => fields.iter().fold(0, |a, &(_, ref v)| a + 4 + v.len(version)),
+                None => 0
+            },
+            CqlUuid(ref o) => match *o {
                 Some(ref u) => u.as_bytes().len(),
                 None => 0     
             },
-            &CqlTimeUuid(ref o) => match *o {
+            CqlTimeUuid(ref o) => match *o {
                 Some(ref u) => u.as_bytes().len(),
                 None => 0     
             },
-            &CqlVarchar(ref o) => match *o {
-                Some(ref s) => s.len() as usize,
+            CqlText(ref o) => match *o {
+                Some(ref s) => s.len(),
+                None => 0
+            },
+            CqlVarchar(ref o) => match *o {
+                Some(ref s) => s.len(),
+                None => 0
+            },
+            CqlVarint(ref o) => match *o {
+                Some(ref v) => v.to_signed_bytes_be().len(),
                 None => 0
             },
-            &CqlVarint(_) => 0,
             _ => 0
         }
     }
@@ -497,8 +909,8 @@ impl<'a> CqlSerializable<'a> for CqlValue {
 
 impl<'a, T:CqlSerializable<'a>, V:CqlSerializable<'a>> CqlSerializable<'a> for Pair<T, V> {
     fn serialize_size<S: std::io::Write>(&'a self, buf: &mut S, bytes_size: CqlBytesSize, version: u8) -> RCResult<()> {
-        self.key.serialize_size(buf, bytes_size, version);
-        self.value.serialize_size(buf, bytes_size, version);
+        try_rc!(self.key.serialize_size(buf, bytes_size, version), "Error serializing Pair (key)");
+        try_rc!(self.value.serialize_size(buf, bytes_size, version), "Error serializing Pair (value)");
         Ok(())
     }
 
@@ -506,7 +918,7 @@ impl<'a, T:CqlSerializable<'a>, V:CqlSerializable<'a>> CqlSerializable<'a> for P
         self.serialize_size(buf, Cqli32, version)
     }
 
-    fn len(&'a self, version: u8) -> usize {
+    fn len(&'a self, _version: u8) -> usize {
         0
     }
 }
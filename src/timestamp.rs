@@ -0,0 +1,48 @@
+extern crate std;
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+///
+/// Source of the default timestamps (microseconds since the epoch) sent with every request.
+///
+pub trait TimestampGenerator {
+    fn next_timestamp(&self) -> i64;
+}
+
+///
+/// Uses the system clock, but never returns the same timestamp twice nor goes back in time if the
+/// clock does: in that case the last timestamp is incremented by one microsecond.
+///
+pub struct MonotonicTimestampGenerator {
+    last: AtomicI64
+}
+
+impl Default for MonotonicTimestampGenerator {
+    fn default() -> MonotonicTimestampGenerator {
+        MonotonicTimestampGenerator::new()
+    }
+}
+
+impl MonotonicTimestampGenerator {
+    pub fn new() -> MonotonicTimestampGenerator {
+        MonotonicTimestampGenerator { last: AtomicI64::new(0) }
+    }
+}
+
+impl TimestampGenerator for MonotonicTimestampGenerator {
+    fn next_timestamp(&self) -> i64 {
+        let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_secs() as i64 * 1_000_000 + elapsed.subsec_micros() as i64,
+            Err(_) => 0
+        };
+        let mut last = self.last.load(Ordering::SeqCst);
+        loop {
+            let next = if now > last { now } else { last + 1 };
+            match self.last.compare_exchange(last, next, Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => return next,
+                Err(current) => last = current
+            }
+        }
+    }
+}
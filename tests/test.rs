@@ -1,4 +1,3 @@
-#[macro_use]
 extern crate cql;
 
 macro_rules! assert_response(
@@ -15,7 +14,7 @@ macro_rules! try_test(
         match $call {
             Ok(val) => val,
             Err(ref err) => panic!("Test failed at library call: {}", err.description())
-        };
+        }
     }
 );
 
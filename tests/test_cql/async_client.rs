@@ -0,0 +1,112 @@
+use std::borrow::Cow;
+
+#[tokio::test]
+#[ignore = "needs a Cassandra node at 127.0.0.1:9042, run with --ignored"]
+async fn test_async() {
+    let creds = vec![Cow::Borrowed("cassandra"), Cow::Borrowed("cassandra")];
+    let client = try_test!(cql::async_client::connect("127.0.0.1", 9042, Some(&creds)).await, "Error connecting to server at 127.0.0.1:9042");
+    println!("Connected (async) with CQL binary version v{}", client.version);
+
+    let mut response = try_test!(client.exec_query("create keyspace if not exists rust with replication = {'class': 'SimpleStrategy', 'replication_factor':1}", &[], cql::Consistency::One).await, "Error creating keyspace");
+    assert_response!(response);
+    response = try_test!(client.exec_query("create table if not exists rust.test (id text primary key, f32 float, f64 double, i32 int, i64 bigint, b boolean, ip inet)", &[], cql::Consistency::One).await, "Error creating table rust.test");
+    assert_response!(response);
+
+    let preps = try_test!(client.prepared_statement("insert into rust.test (id, f32) values (?, ?)").await, "Error creating prepared statement");
+    let params: &[cql::CqlValue] = &[cql::CqlVarchar(Some(Cow::Borrowed("async"))), cql::CqlFloat(Some(1.5))];
+    response = try_test!(client.exec_prepared(&preps, params, cql::Consistency::One).await, "Error executing prepared statement");
+    assert_response!(response);
+
+    // Both queries are in flight on the same connection
+    let (first, second) = tokio::join!(client.exec_query("select * from rust.test", &[], cql::Consistency::One),
+                                       client.exec_query("select id from rust.test", &[], cql::Consistency::One));
+    response = try_test!(first, "Error selecting from table test");
+    assert_response!(response);
+    response = try_test!(second, "Error selecting from table test");
+    assert_response!(response);
+    println!("Result: {:?} \n", response);
+}
+
+#[tokio::test]
+async fn test_async_skip_metadata() {
+    let port = super::fake_server::start();
+    let mut client = try_test!(cql::async_client::connect("127.0.0.1", port, None).await, "Error connecting to the fake server");
+    assert_eq!(client.version, 4);
+
+    let preps = try_test!(client.prepared_statement("select v from ks.t").await, "Error preparing statement");
+    for &skip_metadata in [false, true].iter() {
+        client.skip_metadata = skip_metadata;
+        let response = try_test!(client.exec_prepared(&preps, &[], cql::Consistency::One).await, "Error executing prepared statement");
+        match response.body {
+            cql::CqlResponseBody::ResultRows(rows) => {
+                assert_eq!(rows.metadata.flags & 0x0004 != 0, skip_metadata);
+                assert_eq!(rows.rows.len(), 1);
+                match rows.rows[0].cols[..] {
+                    [cql::CqlVarchar(Some(ref value))] => assert_eq!(value, "value"),
+                    ref cols => panic!("Unexpected columns {:?}", cols)
+                }
+            },
+            body => panic!("Unexpected body {:?}", body)
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_async_compressed_startup() {
+    let port = super::fake_server::start();
+    for &compression in [cql::Compression::Lz4, cql::Compression::Snappy].iter() {
+        let mut options = cql::ConnectOptions::new();
+        options.compression = vec![compression];
+        options.request_timeout = Some(std::time::Duration::from_millis(200));
+        let client = try_test!(cql::async_client::connect_with_options("127.0.0.1", port, None, &options).await, "Error connecting to the fake server");
+        assert_eq!(client.compression, compression);
+        match client.exec_query("select v from ks.t", &[], cql::Consistency::One).await {
+            Ok(cql::CqlResponse { body: cql::CqlResponseBody::ResultVoid, .. }) => (),
+            response => panic!("Unexpected response {:?}", response)
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_async_register() {
+    let port = super::fake_server::start();
+    let client = try_test!(cql::async_client::connect("127.0.0.1", port, None).await, "Error connecting to the fake server");
+
+    let mut events = try_test!(client.register(&[cql::CqlEventType::StatusChange]).await, "Error registering for events");
+    match events.recv().await {
+        Some(cql::CqlEvent::StatusChange { change, port, .. }) => {
+            assert_eq!(change, cql::StatusChangeType::Up);
+            assert_eq!(port, 9042);
+        },
+        event => panic!("Unexpected event {:?}", event)
+    }
+    // Requests keep working once events are pushed on the connection
+    let response = try_test!(client.exec_query("select v from ks.t", &[], cql::Consistency::One).await, "Error executing query");
+    assert_response!(response);
+}
+
+async fn query_flags(client: &cql::async_client::AsyncClient, options: cql::QueryOptions) -> u8 {
+    match client.exec_query("flags", &[], options).await {
+        Ok(cql::CqlResponse { body: cql::CqlResponseBody::ResultKeyspace(flags), .. }) => flags.parse().unwrap(),
+        response => panic!("Unexpected response {:?}", response)
+    }
+}
+
+#[tokio::test]
+async fn test_async_client_defaults() {
+    let port = super::fake_server::start();
+    let mut client = try_test!(cql::async_client::connect("127.0.0.1", port, None).await, "Error connecting to the fake server");
+    let one = cql::QueryOptions::new(cql::Consistency::One);
+
+    // Timestamps are generated by the client by default (0x20), there is no serial consistency (0x10)
+    assert_eq!(query_flags(&client, one.clone()).await & 0x30, 0x20);
+
+    client.serial_consistency = Some(cql::Consistency::LocalSerial);
+    client.set_timestamp_generator(None);
+    assert_eq!(query_flags(&client, one.clone()).await & 0x30, 0x10);
+
+    // Explicit options win over the defaults of the client
+    let mut options = one.clone();
+    options.timestamp = Some(1);
+    assert_eq!(query_flags(&client, options).await & 0x30, 0x30);
+}
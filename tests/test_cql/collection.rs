@@ -0,0 +1,106 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+use cql::*;
+use cql::reader::CqlReader;
+use cql::serialize::CqlSerializable;
+
+// [option] descriptors of the types used below
+static INT: [u8; 2] = [0x00, 0x09];
+static LIST_INT: [u8; 4] = [0x00, 0x20, 0x00, 0x09];
+static SET_VARCHAR: [u8; 4] = [0x00, 0x22, 0x00, 0x0D];
+static MAP_VARCHAR_INT: [u8; 6] = [0x00, 0x21, 0x00, 0x0D, 0x00, 0x09];
+static TUPLE_INT_VARCHAR: [u8; 8] = [0x00, 0x31, 0x00, 0x02, 0x00, 0x09, 0x00, 0x0D];
+static LIST_TUPLE: [u8; 10] = [0x00, 0x20, 0x00, 0x31, 0x00, 0x02, 0x00, 0x09, 0x00, 0x0D];
+
+fn int(v: i32) -> CqlValue {
+    CqlValue::CqlInt(Some(v))
+}
+
+fn varchar(v: &'static str) -> CqlValue {
+    CqlVarchar(Some(Cow::Borrowed(v)))
+}
+
+// Serializes the value as a bound value of the version, then reads it back as a column of the given type
+fn assert_round_trip(value: CqlValue, col_type: &[u8], version: u8, bytes: &[u8]) {
+    let mut buf = vec![];
+    try_test!(value.serialize(&mut buf, version), "Error serializing value");
+    assert_eq!(buf, bytes.to_vec(), "v{} {:?}", version, value);
+    assert_eq!(value.len(version), bytes.len() - 4, "v{} {:?}", version, value);
+
+    let col_type = try_test!(Cursor::new(col_type).read_cql_type(), "Error reading type");
+    // As for a row value, the element sizes of the version only apply to collections
+    let value_size = match col_type {
+        CqlType::List(_) | CqlType::Map(_, _) | CqlType::Set(_) if version < 3 => CqlBytesSize::Cqli16,
+        _ => CqlBytesSize::Cqli32
+    };
+    let mut reader = Cursor::new(bytes);
+    let read = try_test!(reader.read_cql_typed_value(&col_type, value_size), "Error reading value");
+    assert_eq!(format!("{:?}", read), format!("{:?}", value));
+    assert_eq!(reader.position() as usize, bytes.len());
+}
+
+// Collections are [bytes] whose element count and element lengths are [short] before v3 and [int] since.
+// Each element is written, not only the count of them
+#[test]
+fn test_list() {
+    let list = || CqlValue::CqlList(Some(vec![int(1), int(2)]));
+    assert_round_trip(list(), &LIST_INT, 2, &[0x00, 0x00, 0x00, 0x0E, 0x00, 0x02,
+                                              0x00, 0x04, 0x00, 0x00, 0x00, 0x01,
+                                              0x00, 0x04, 0x00, 0x00, 0x00, 0x02]);
+    assert_round_trip(list(), &LIST_INT, 3, &[0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x02,
+                                              0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01,
+                                              0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x02]);
+
+    assert_round_trip(CqlValue::CqlList(Some(vec![])), &LIST_INT, 2, &[0x00, 0x00, 0x00, 0x02, 0x00, 0x00]);
+    assert_round_trip(CqlValue::CqlList(Some(vec![])), &LIST_INT, 4, &[0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00]);
+    for &version in [2u8, 4].iter() {
+        assert_round_trip(CqlValue::CqlList(None), &LIST_INT, version, &[0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+}
+
+#[test]
+fn test_set() {
+    let set = || CqlValue::CqlSet(Some(vec![varchar("a")]));
+    assert_round_trip(set(), &SET_VARCHAR, 2, &[0x00, 0x00, 0x00, 0x05, 0x00, 0x01, 0x00, 0x01, 0x61]);
+    assert_round_trip(set(), &SET_VARCHAR, 4, &[0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x61]);
+}
+
+#[test]
+fn test_map() {
+    let map = || CqlValue::CqlMap(Some(vec![Pair { key: varchar("a"), value: int(1) }]));
+    assert_round_trip(map(), &MAP_VARCHAR_INT, 2, &[0x00, 0x00, 0x00, 0x0B, 0x00, 0x01,
+                                                    0x00, 0x01, 0x61,
+                                                    0x00, 0x04, 0x00, 0x00, 0x00, 0x01]);
+    assert_round_trip(map(), &MAP_VARCHAR_INT, 4, &[0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x00, 0x01,
+                                                    0x00, 0x00, 0x00, 0x01, 0x61,
+                                                    0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01]);
+}
+
+// The components of a tuple are [bytes] with an [int] length in every version
+#[test]
+fn test_tuple() {
+    let tuple = || CqlValue::CqlTuple(Some(vec![int(1), CqlVarchar(None)]));
+    for &version in [2u8, 3, 4].iter() {
+        assert_round_trip(tuple(), &TUPLE_INT_VARCHAR, version, &[0x00, 0x00, 0x00, 0x0C,
+                                                                  0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01,
+                                                                  0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_round_trip(CqlValue::CqlTuple(None), &TUPLE_INT_VARCHAR, version, &[0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+
+    // Unlike UDT fields, trailing components cannot be left out
+    let mut reader = Cursor::new(&[0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01]);
+    let types = vec![try_test!(Cursor::new(&INT[..]).read_cql_type(), "Error reading type"); 2];
+    assert!(reader.read_cql_tuple(&types, CqlBytesSize::Cqli32).is_err());
+}
+
+// Inside a collection the tuple length follows the collection, its components stay [int]
+#[test]
+fn test_tuple_in_list() {
+    let list = || CqlValue::CqlList(Some(vec![CqlValue::CqlTuple(Some(vec![int(1), varchar("a")]))]));
+    assert_round_trip(list(), &LIST_TUPLE, 2, &[0x00, 0x00, 0x00, 0x11, 0x00, 0x01, 0x00, 0x0D,
+                                                0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01,
+                                                0x00, 0x00, 0x00, 0x01, 0x61]);
+    assert_round_trip(list(), &LIST_TUPLE, 3, &[0x00, 0x00, 0x00, 0x15, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x0D,
+                                                0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01,
+                                                0x00, 0x00, 0x00, 0x01, 0x61]);
+}
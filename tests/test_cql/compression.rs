@@ -0,0 +1,73 @@
+use cql::Compression;
+use cql::compression::*;
+
+// "abc" repeated 12 times as Cassandra sends it with LZ4: the uncompressed length as a big endian
+// [int], then a block with the literals "abc", a match of length 27 at offset 3 and the literals "abcabc"
+static ABC_LZ4: [u8; 18] = [0x00, 0x00, 0x00, 0x24,
+                            0x3F, 0x61, 0x62, 0x63, 0x03, 0x00, 0x08,
+                            0x60, 0x61, 0x62, 0x63, 0x61, 0x62, 0x63];
+
+fn body() -> Vec<u8> {
+    (0 .. 10000).map(|i| (i % 7) as u8).collect()
+}
+
+#[test]
+fn test_lz4() {
+    let body = body();
+    let compressed = compress(&body, Compression::Lz4).unwrap();
+    assert_eq!(compressed[.. 4].to_vec(), vec![0x00, 0x00, 0x27, 0x10]);
+    assert!(compressed.len() < body.len());
+    assert_eq!(decompress(&compressed, Compression::Lz4).unwrap(), body);
+    assert_eq!(decompress(&compress(&[], Compression::Lz4).unwrap(), Compression::Lz4).unwrap(), Vec::<u8>::new());
+
+    assert_eq!(decompress(&ABC_LZ4, Compression::Lz4).unwrap(), b"abc".repeat(12));
+}
+
+#[test]
+fn test_lz4_invalid() {
+    // A corrupt length prefix must not be trusted for the allocation
+    let mut corrupted = ABC_LZ4.to_vec();
+    corrupted[0] = 0xFF;
+    assert!(decompress(&corrupted, Compression::Lz4).is_err());
+    assert!(decompress(&ABC_LZ4[.. 3], Compression::Lz4).is_err());
+    assert!(decompress(&ABC_LZ4[.. 8], Compression::Lz4).is_err());
+}
+
+// "abc" compressed with Snappy: the uncompressed length as a varint and a literal of length 3
+static ABC_SNAPPY: [u8; 5] = [0x03, 0x08, 0x61, 0x62, 0x63];
+
+#[test]
+fn test_snappy() {
+    let body = body();
+    let compressed = compress(&body, Compression::Snappy).unwrap();
+    assert!(compressed.len() < body.len());
+    assert_eq!(decompress(&compressed, Compression::Snappy).unwrap(), body);
+
+    assert_eq!(compress(b"abc", Compression::Snappy).unwrap(), ABC_SNAPPY.to_vec());
+    assert_eq!(decompress(&ABC_SNAPPY, Compression::Snappy).unwrap(), b"abc".to_vec());
+    assert!(decompress(&ABC_SNAPPY[.. 4], Compression::Snappy).is_err());
+}
+
+#[test]
+fn test_no_compression() {
+    assert_eq!(compress(b"abc", Compression::None).unwrap(), b"abc".to_vec());
+    // Compressed frames are an error when no compression was negotiated
+    assert!(decompress(b"abc", Compression::None).is_err());
+}
+
+// The fake server compresses its READY response already, as Cassandra 3.x does
+#[test]
+fn test_compressed_startup() {
+    let port = super::fake_server::start();
+    for &compression in [Compression::Lz4, Compression::Snappy].iter() {
+        let mut options = cql::ConnectOptions::new();
+        options.compression = vec![compression];
+        options.request_timeout = Some(std::time::Duration::from_millis(200));
+        let mut client = try_test!(cql::connect_with_options("127.0.0.1", port, None, &options), "Error connecting to the fake server");
+        assert_eq!(client.compression, compression);
+        match client.exec_query("select v from ks.t", &[], cql::Consistency::One) {
+            Ok(cql::CqlResponse { body: cql::CqlResponseBody::ResultVoid, .. }) => (),
+            response => panic!("Unexpected response {:?}", response)
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::io::Cursor;
+use std::time::Duration;
+use cql::*;
+use cql::datetime::*;
+use cql::reader::CqlReader;
+use cql::serialize::CqlSerializable;
+
+#[test]
+fn test_cql_date() {
+    assert_eq!(cql_date_to_days(1 << 31), 0);
+    assert_eq!(cql_date_to_days(0), -(1 << 31));
+    assert_eq!(days_to_cql_date(-1), Some((1 << 31) - 1));
+    assert_eq!(days_to_cql_date(1 << 31), None);
+    assert_eq!(days_to_cql_date(-(1 << 31) - 1), None);
+}
+
+#[test]
+fn test_cql_time() {
+    let time = Duration::new(3600 + 1, 500);
+    assert_eq!(duration_to_cql_time(time), Some(3_601_000_000_500));
+    assert_eq!(cql_time_to_duration(3_601_000_000_500), Some(time));
+    assert_eq!(duration_to_cql_time(Duration::from_secs(86_400)), None);
+    assert_eq!(cql_time_to_duration(-1), None);
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_chrono_conversions() {
+    use chrono::{NaiveDate, NaiveTime};
+
+    let date = NaiveDate::from_ymd_opt(2015, 5, 3).unwrap();
+    assert_eq!(naive_date_to_cql_date(date), (1 << 31) + 16558);
+    assert_eq!(cql_date_to_naive_date(naive_date_to_cql_date(date)), Some(date));
+    let before_epoch = NaiveDate::from_ymd_opt(1969, 12, 31).unwrap();
+    assert_eq!(naive_date_to_cql_date(before_epoch), (1 << 31) - 1);
+
+    let time = NaiveTime::from_hms_nano_opt(13, 30, 54, 234).unwrap();
+    assert_eq!(naive_time_to_cql_time(time), 48_654_000_000_234);
+    assert_eq!(cql_time_to_naive_time(48_654_000_000_234), Some(time));
+    assert_eq!(cql_time_to_naive_time(86_400_000_000_000), None);
+}
+
+fn read_value(type_id: u8, bytes: &[u8]) -> RCResult<CqlValue> {
+    let col_type = try_test!(Cursor::new(&[0x00, type_id]).read_cql_type(), "Error reading type");
+    let mut reader = Cursor::new(bytes);
+    let value = reader.read_cql_typed_value(&col_type, CqlBytesSize::Cqli32);
+    if value.is_ok() {
+        assert_eq!(reader.position() as usize, bytes.len());
+    }
+    value
+}
+
+// Reads the [bytes] as a column of the type with the given id and serializes the value back
+fn assert_round_trip(type_id: u8, bytes: &[u8]) -> CqlValue {
+    let value = try_test!(read_value(type_id, bytes), "Error reading value");
+    let mut buf = vec![];
+    try_test!(value.serialize(&mut buf, 4), "Error serializing value");
+    assert_eq!(buf, bytes.to_vec(), "{:?}", value);
+    assert_eq!(value.len(4), bytes.len() - 4);
+    value
+}
+
+// Values of a fixed size are rejected when their length differs, including empty ones
+fn assert_wrong_lengths(type_id: u8, size: usize) {
+    for &len in [0, size - 1, size + 1].iter() {
+        let mut bytes = vec![0x00, 0x00, 0x00, len as u8];
+        bytes.extend(vec![0x00; len]);
+        assert!(read_value(type_id, &bytes).is_err(), "0x{:02X} of length {}", type_id, len);
+    }
+    assert_round_trip(type_id, &[0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+fn test_date_bytes() {
+    match assert_round_trip(0x11, &[0x00, 0x00, 0x00, 0x04, 0x80, 0x00, 0x40, 0xAE]) {
+        CqlValue::CqlDate(Some(date)) => assert_eq!(cql_date_to_days(date), 16558),
+        value => panic!("Unexpected value {:?}", value)
+    }
+    match assert_round_trip(0x11, &[0x00, 0x00, 0x00, 0x04, 0x7F, 0xFF, 0xFF, 0xFF]) {
+        CqlValue::CqlDate(Some(date)) => assert_eq!(cql_date_to_days(date), -1),
+        value => panic!("Unexpected value {:?}", value)
+    }
+    assert_wrong_lengths(0x11, 4);
+}
+
+#[test]
+fn test_time_bytes() {
+    // Nanoseconds since midnight, 13:30:54.000000234
+    match assert_round_trip(0x12, &[0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x2C, 0x40, 0x24, 0x63, 0x0C, 0xEA]) {
+        CqlValue::CqlTime(Some(time)) => assert_eq!(cql_time_to_duration(time), Some(Duration::new(48_654, 234))),
+        value => panic!("Unexpected value {:?}", value)
+    }
+    assert_wrong_lengths(0x12, 8);
+}
+
+#[test]
+fn test_smallint_bytes() {
+    match assert_round_trip(0x13, &[0x00, 0x00, 0x00, 0x02, 0x01, 0x2C]) {
+        CqlValue::CqlSmallInt(Some(v)) => assert_eq!(v, 300),
+        value => panic!("Unexpected value {:?}", value)
+    }
+    match assert_round_trip(0x13, &[0x00, 0x00, 0x00, 0x02, 0xFF, 0xFE]) {
+        CqlValue::CqlSmallInt(Some(v)) => assert_eq!(v, -2),
+        value => panic!("Unexpected value {:?}", value)
+    }
+    assert_wrong_lengths(0x13, 2);
+}
+
+#[test]
+fn test_tinyint_bytes() {
+    match assert_round_trip(0x14, &[0x00, 0x00, 0x00, 0x01, 0x7F]) {
+        CqlValue::CqlTinyInt(Some(v)) => assert_eq!(v, 127),
+        value => panic!("Unexpected value {:?}", value)
+    }
+    match assert_round_trip(0x14, &[0x00, 0x00, 0x00, 0x01, 0xFF]) {
+        CqlValue::CqlTinyInt(Some(v)) => assert_eq!(v, -1),
+        value => panic!("Unexpected value {:?}", value)
+    }
+    assert_wrong_lengths(0x14, 1);
+}
@@ -0,0 +1,82 @@
+use std::io::Cursor;
+use std::str::FromStr;
+use cql::*;
+use cql::reader::CqlReader;
+use cql::serialize::CqlSerializable;
+
+#[test]
+fn test_decimal_format() {
+    let d = Decimal::new(num::BigInt::from(-123450), 2);
+    assert_eq!(d.to_string(), "-1234.50");
+    assert_eq!(Decimal::new(num::BigInt::from(5), 3).to_string(), "0.005");
+    assert_eq!(Decimal::new(num::BigInt::from(-5), 1).to_string(), "-0.5");
+    assert_eq!(Decimal::new(num::BigInt::from(12), -2).to_string(), "1200");
+    assert_eq!(Decimal::new(num::BigInt::from(0), 0).to_string(), "0");
+}
+
+#[test]
+fn test_decimal_parse() {
+    assert_eq!(Decimal::from_str("-1234.50").unwrap(), Decimal::new(num::BigInt::from(-123450), 2));
+    assert_eq!(Decimal::from_str("0.005").unwrap(), Decimal::new(num::BigInt::from(5), 3));
+    assert_eq!(Decimal::from_str("42").unwrap(), Decimal::new(num::BigInt::from(42), 0));
+    assert_eq!(Decimal::from_str("123456789012345678901234567890.1").unwrap().to_string(), "123456789012345678901234567890.1");
+    assert!(Decimal::from_str("1.-5").is_err());
+    assert!(Decimal::from_str("abc").is_err());
+    assert!(Decimal::from_str("").is_err());
+}
+
+fn serialize(value: CqlValue) -> Vec<u8> {
+    let mut buf = vec![];
+    try_test!(value.serialize(&mut buf, 4), "Error serializing value");
+    assert_eq!(buf.len(), 4 + value.len(4));
+    buf
+}
+
+// Each value with its [bytes] encoding: the varint is the minimal two's complement, big endian
+fn varints() -> Vec<(num::BigInt, Vec<u8>)> {
+    vec![(num::BigInt::from(0), vec![0x00, 0x00, 0x00, 0x01, 0x00]),
+         (num::BigInt::from(127), vec![0x00, 0x00, 0x00, 0x01, 0x7F]),
+         (num::BigInt::from(128), vec![0x00, 0x00, 0x00, 0x02, 0x00, 0x80]),
+         (num::BigInt::from(-1), vec![0x00, 0x00, 0x00, 0x01, 0xFF]),
+         (num::BigInt::from(-128), vec![0x00, 0x00, 0x00, 0x01, 0x80]),
+         (num::BigInt::from(-129), vec![0x00, 0x00, 0x00, 0x02, 0xFF, 0x7F]),
+         (num::BigInt::from(1u128 << 64), vec![0x00, 0x00, 0x00, 0x09, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+         (-num::BigInt::from(1u128 << 64), vec![0x00, 0x00, 0x00, 0x09, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])]
+}
+
+#[test]
+fn test_varint_bytes() {
+    for (value, bytes) in varints() {
+        assert_eq!(serialize(CqlValue::CqlVarint(Some(value.clone()))), bytes, "{}", value);
+        let mut reader = Cursor::new(&bytes);
+        assert_eq!(reader.read_cql_varint(CqlBytesSize::Cqli32).unwrap(), Some(value));
+        assert_eq!(reader.position() as usize, bytes.len());
+    }
+    assert_eq!(serialize(CqlValue::CqlVarint(None)), vec![0xFF, 0xFF, 0xFF, 0xFF]);
+    assert!(Cursor::new(&[0xFF, 0xFF, 0xFF, 0xFF]).read_cql_varint(CqlBytesSize::Cqli32).unwrap().is_none());
+}
+
+// The scale as an [int] precedes the unscaled value as a varint
+fn decimals() -> Vec<(Decimal, Vec<u8>)> {
+    vec![(Decimal::new(num::BigInt::from(0), 0), vec![0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00]),
+         (Decimal::new(num::BigInt::from(-123450), 2), vec![0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x02, 0xFE, 0x1D, 0xC6]),
+         (Decimal::new(num::BigInt::from(12), -2), vec![0x00, 0x00, 0x00, 0x05, 0xFF, 0xFF, 0xFF, 0xFE, 0x0C]),
+         (Decimal::new(num::BigInt::from(-5), 1), vec![0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x01, 0xFB]),
+         (Decimal::new(num::BigInt::from(1u128 << 64), 3),
+          vec![0x00, 0x00, 0x00, 0x0D, 0x00, 0x00, 0x00, 0x03, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])]
+}
+
+#[test]
+fn test_decimal_bytes() {
+    for (value, bytes) in decimals() {
+        assert_eq!(serialize(CqlValue::CqlDecimal(Some(value.clone()))), bytes, "{}", value);
+        let mut reader = Cursor::new(&bytes);
+        assert_eq!(reader.read_cql_decimal(CqlBytesSize::Cqli32).unwrap(), Some(value));
+        assert_eq!(reader.position() as usize, bytes.len());
+    }
+    assert_eq!(serialize(CqlValue::CqlDecimal(None)), vec![0xFF, 0xFF, 0xFF, 0xFF]);
+
+    // A decimal needs at least its scale
+    let mut reader = Cursor::new(&[0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x02]);
+    assert!(reader.read_cql_decimal(CqlBytesSize::Cqli32).is_err());
+}
@@ -0,0 +1,98 @@
+use std::io::Cursor;
+use std::str::FromStr;
+use cql::*;
+use cql::reader::CqlReader;
+use cql::serialize::*;
+
+const HOUR: i64 = 3_600_000_000_000;
+const MINUTE: i64 = 60_000_000_000;
+const SECOND: i64 = 1_000_000_000;
+
+#[test]
+fn test_duration_format() {
+    assert_eq!(Duration::new(14, 3, HOUR + 30 * MINUTE).to_string(), "1y2mo3d1h30m");
+    assert_eq!(Duration::new(0, -2, -10 * HOUR).to_string(), "-2d10h");
+    assert_eq!(Duration::new(0, 0, 6 * SECOND + 7_008_009).to_string(), "6s7ms8us9ns");
+    assert_eq!(Duration::new(0, 0, 0).to_string(), "0s");
+}
+
+#[test]
+fn test_duration_parse() {
+    assert_eq!(Duration::from_str("1h30m").unwrap(), Duration::new(0, 0, HOUR + 30 * MINUTE));
+    assert_eq!(Duration::from_str("-2d10h").unwrap(), Duration::new(0, -2, -10 * HOUR));
+    assert_eq!(Duration::from_str("1y2mo3w4d").unwrap(), Duration::new(14, 25, 0));
+    assert_eq!(Duration::from_str("5ms6us7ns").unwrap(), Duration::new(0, 0, 5_006_007));
+    assert_eq!(Duration::from_str("5µs").unwrap(), Duration::new(0, 0, 5_000));
+    assert_eq!(Duration::from_str("P1Y2M3DT4H5M6S").unwrap(), Duration::new(14, 3, 4 * HOUR + 5 * MINUTE + 6 * SECOND));
+    assert_eq!(Duration::from_str("P2W").unwrap(), Duration::new(0, 14, 0));
+    assert_eq!(Duration::from_str("PT30M").unwrap(), Duration::new(0, 0, 30 * MINUTE));
+    assert_eq!(Duration::from_str("-P0001-02-03T04:05:06").unwrap(), Duration::new(-14, -3, -(4 * HOUR + 5 * MINUTE + 6 * SECOND)));
+    let d = Duration::new(26, 9, 12 * HOUR + 1);
+    assert_eq!(Duration::from_str(&d.to_string()).unwrap(), d);
+
+    assert!(Duration::from_str("").is_err());
+    assert!(Duration::from_str("P").is_err());
+    assert!(Duration::from_str("PT").is_err());
+    assert!(Duration::from_str("30m1h").is_err());
+    assert!(Duration::from_str("1x").is_err());
+    assert!(Duration::from_str("h").is_err());
+    assert!(Duration::from_str("3000000000d").is_err());
+}
+
+// Zigzag encoded values and their variable length encodings
+static VINTS: [(i64, &[u8]); 14] = [
+    (0, &[0x00]),
+    (1, &[0x02]),
+    (-1, &[0x01]),
+    (63, &[0x7E]),
+    (-63, &[0x7D]),
+    (-64, &[0x7F]),
+    (64, &[0x80, 0x80]),
+    (-65, &[0x80, 0x81]),
+    (i32::MAX as i64, &[0xF0, 0xFF, 0xFF, 0xFF, 0xFE]),
+    (i32::MIN as i64, &[0xF0, 0xFF, 0xFF, 0xFF, 0xFF]),
+    (1 << 55, &[0xFF, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ((1 << 55) - 1, &[0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE]),
+    (i64::MAX, &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE]),
+    (i64::MIN, &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]),
+];
+
+#[test]
+fn test_vint() {
+    for &(value, encoded) in VINTS.iter() {
+        assert_eq!(encode_vint(value), encoded.to_vec(), "{}", value);
+        let mut reader = Cursor::new(encoded);
+        assert_eq!(reader.read_cql_vint().unwrap(), value, "{:?}", encoded);
+        assert_eq!(reader.position() as usize, encoded.len());
+    }
+    assert!(Cursor::new(&[0xFF, 0xFF][..]).read_cql_vint().is_err());
+}
+
+fn read_duration(data: &[u8]) -> RCResult<Option<Duration>> {
+    let mut buf = vec![0x00, 0x00, 0x00, data.len() as u8];
+    buf.extend(data);
+    Cursor::new(buf).read_cql_duration(CqlBytesSize::Cqli32)
+}
+
+#[test]
+fn test_duration_serialize() {
+    let d = Duration::new(-14, -3, -HOUR);
+    let mut buf = vec![];
+    CqlValue::CqlDuration(Some(d)).serialize(&mut buf, 4).unwrap();
+    assert_eq!(read_duration(&buf[4 ..]).unwrap(), Some(d));
+
+    // All the components must have the same sign
+    assert!(!Duration::new(1, -1, 0).is_valid());
+    assert!(Duration::new(0, 0, -1).is_valid());
+    assert!(CqlValue::CqlDuration(Some(Duration::new(1, 0, -1))).serialize(&mut vec![], 4).is_err());
+    assert!(read_duration(&[0x02, 0x00, 0x01]).is_err());
+
+    // Months and days are [int]s
+    let mut months = encode_vint(i32::MAX as i64 + 1);
+    months.extend(&[0x00, 0x00]);
+    assert!(read_duration(&months).is_err());
+    let mut days = vec![0x00];
+    days.extend(encode_vint(i32::MIN as i64 - 1));
+    days.push(0x00);
+    assert!(read_duration(&days).is_err());
+}
@@ -0,0 +1,165 @@
+use std::io::Cursor;
+use cql::*;
+use cql::reader::CqlReader;
+
+// Encodes the [string]s of an event body, so the fixtures read as the spec describes them
+fn strings(values: &[&str]) -> Vec<u8> {
+    let mut buf = vec![];
+    for value in values.iter() {
+        buf.extend(&[(value.len() >> 8) as u8, value.len() as u8]);
+        buf.extend(value.as_bytes());
+    }
+    buf
+}
+
+// 127.0.0.1:9042 as an [inet]
+static INET_V4: [u8; 9] = [0x04, 0x7F, 0x00, 0x00, 0x01, 0x00, 0x00, 0x23, 0x52];
+
+// [2001:db8::1]:9043 as an [inet]
+static INET_V6: [u8; 21] = [0x10, 0x20, 0x01, 0x0D, 0xB8, 0x00, 0x00, 0x00, 0x00,
+                            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+                            0x00, 0x00, 0x23, 0x53];
+
+fn read_event(data: &[u8], version: u8) -> CqlEvent {
+    let mut reader = Cursor::new(data);
+    let event = reader.read_cql_event(version).unwrap();
+    assert_eq!(reader.position() as usize, data.len());
+    event
+}
+
+#[test]
+fn test_topology_change() {
+    let mut data = strings(&["TOPOLOGY_CHANGE", "NEW_NODE"]);
+    data.extend(&INET_V4);
+    match read_event(&data, 4) {
+        CqlEvent::TopologyChange { change, address, port } => {
+            assert_eq!(change, TopologyChangeType::NewNode);
+            assert_eq!(format!("{:?}", address), "Ipv4(127.0.0.1)");
+            assert_eq!(port, 9042);
+        },
+        event => panic!("Unexpected event {:?}", event)
+    }
+
+    let mut data = strings(&["TOPOLOGY_CHANGE", "REMOVED_NODE"]);
+    data.extend(&INET_V6);
+    match read_event(&data, 4) {
+        CqlEvent::TopologyChange { change, address, port } => {
+            assert_eq!(change, TopologyChangeType::RemovedNode);
+            assert_eq!(format!("{:?}", address), "Ipv6(2001:db8::1)");
+            assert_eq!(port, 9043);
+        },
+        event => panic!("Unexpected event {:?}", event)
+    }
+
+    let mut data = strings(&["TOPOLOGY_CHANGE", "EXPLODED_NODE"]);
+    data.extend(&INET_V4);
+    assert!(Cursor::new(data).read_cql_event(4).is_err());
+}
+
+#[test]
+fn test_status_change() {
+    let mut data = strings(&["STATUS_CHANGE", "UP"]);
+    data.extend(&INET_V4);
+    match read_event(&data, 4) {
+        CqlEvent::StatusChange { change, address, port } => {
+            assert_eq!(change, StatusChangeType::Up);
+            assert_eq!(format!("{:?}", address), "Ipv4(127.0.0.1)");
+            assert_eq!(port, 9042);
+        },
+        event => panic!("Unexpected event {:?}", event)
+    }
+
+    let mut data = strings(&["STATUS_CHANGE", "DOWN"]);
+    data.extend(&INET_V6);
+    match read_event(&data, 4) {
+        CqlEvent::StatusChange { change, address, port } => {
+            assert_eq!(change, StatusChangeType::Down);
+            assert_eq!(format!("{:?}", address), "Ipv6(2001:db8::1)");
+            assert_eq!(port, 9043);
+        },
+        event => panic!("Unexpected event {:?}", event)
+    }
+
+    // Only 4 and 16 byte addresses exist
+    let mut data = strings(&["STATUS_CHANGE", "UP"]);
+    data.extend(&[0x05, 0x7F, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x23, 0x52]);
+    assert!(Cursor::new(data).read_cql_event(4).is_err());
+}
+
+fn read_schema_change(data: &[u8], version: u8) -> CqlSchemaChange {
+    match read_event(data, version) {
+        CqlEvent::SchemaChange(change) => change,
+        event => panic!("Unexpected event {:?}", event)
+    }
+}
+
+#[test]
+fn test_schema_change() {
+    let change = read_schema_change(&strings(&["SCHEMA_CHANGE", "CREATED", "KEYSPACE", "rust"]), 4);
+    assert_eq!(change.change, "CREATED");
+    assert_eq!(change.target, "KEYSPACE");
+    assert_eq!(change.keyspace, "rust");
+    assert_eq!(change.name, "");
+    assert!(change.arg_types.is_empty());
+
+    let change = read_schema_change(&strings(&["SCHEMA_CHANGE", "UPDATED", "TABLE", "rust", "test"]), 4);
+    assert_eq!(change.change, "UPDATED");
+    assert_eq!(change.target, "TABLE");
+    assert_eq!(change.keyspace, "rust");
+    assert_eq!(change.name, "test");
+    assert!(change.arg_types.is_empty());
+
+    // Functions carry their argument types as a [string list]
+    let mut data = strings(&["SCHEMA_CHANGE", "DROPPED", "FUNCTION", "rust", "plus"]);
+    data.extend(&[0x00, 0x02]);
+    data.extend(strings(&["int", "bigint"]));
+    let change = read_schema_change(&data, 4);
+    assert_eq!(change.change, "DROPPED");
+    assert_eq!(change.target, "FUNCTION");
+    assert_eq!(change.keyspace, "rust");
+    assert_eq!(change.name, "plus");
+    assert_eq!(change.arg_types, vec!["int", "bigint"]);
+}
+
+#[test]
+fn test_schema_change_v2() {
+    // Before v3 there is no target: an empty table name means a keyspace change
+    let change = read_schema_change(&strings(&["SCHEMA_CHANGE", "CREATED", "rust", ""]), 2);
+    assert_eq!(change.change, "CREATED");
+    assert_eq!(change.target, "KEYSPACE");
+    assert_eq!(change.keyspace, "rust");
+    assert_eq!(change.name, "");
+
+    let change = read_schema_change(&strings(&["SCHEMA_CHANGE", "DROPPED", "rust", "test"]), 1);
+    assert_eq!(change.change, "DROPPED");
+    assert_eq!(change.target, "TABLE");
+    assert_eq!(change.keyspace, "rust");
+    assert_eq!(change.name, "test");
+}
+
+#[test]
+fn test_event_frame_v2() {
+    // Events are pushed on stream -1, which v1/v2 headers encode as a single signed byte
+    let body = strings(&["SCHEMA_CHANGE", "CREATED", "rust", "test"]);
+    let mut data = vec![0x82, 0x00, 0xFF, 0x0C, 0x00, 0x00, 0x00, body.len() as u8];
+    data.extend(&body);
+
+    let mut reader = Cursor::new(data);
+    let header = reader.read_cql_frame_header(2).unwrap();
+    assert_eq!(header.stream, -1);
+    let response = reader.read_cql_response_body(header, 2, Compression::None, None).unwrap();
+    assert_eq!(response.stream, -1);
+    match response.body {
+        CqlResponseBody::ResponseEvent(CqlEvent::SchemaChange(change)) => {
+            assert_eq!(change.target, "TABLE");
+            assert_eq!(change.name, "test");
+        },
+        body => panic!("Unexpected body {:?}", body)
+    }
+
+    // Stream 0x80 is negative as well in v2, but a regular stream in v3+
+    let mut reader = Cursor::new(vec![0x82, 0x00, 0x80, 0x0C]);
+    assert_eq!(reader.read_cql_frame_header(2).unwrap().stream, -128);
+    let mut reader = Cursor::new(vec![0x84, 0x00, 0x00, 0x80, 0x0C]);
+    assert_eq!(reader.read_cql_frame_header(4).unwrap().stream, 128);
+}
@@ -0,0 +1,341 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use cql::Compression;
+use cql::compression::{compress, decompress};
+
+// A server speaking just enough of protocol v4 to test the clients without a Cassandra node. It only
+// advertises v3 and v4, so clients settle on v4 after their first OPTIONS request. QUERY requests
+// are answered according to their text:
+//  - "silent": answered with the keyspace "late" after `LATE_RESPONSE_DELAY`, other requests are answered meanwhile
+//  - "once": answered as "silent" the first time on a connection, with a VOID result afterwards
+//  - "close": the connection is closed without answering
+//  - "flags": answered with the query flags of the request as the keyspace, e.g. "48"
+//  - "pages": the rows "a" and "b" of ks.t with the paging state "p2" when the request has no paging state,
+//    the last row "c" when it has "p2". The page size of the request is ignored.
+//  - the trace session queries of `get_trace_session`: `TRACED_SESSION` is still being written the first
+//    time it is read on a connection and complete afterwards, with one event. Other sessions have no rows.
+//  - anything else: VOID result
+// Requests bigger than `STALL_LENGTH` are never read, so the client eventually fails to write them.
+// PREPARE returns the statement "p1" with a single varchar column "v", whose executions return one row
+// with "value", without the result metadata when the client asks to skip it. REGISTER is followed by a
+// STATUS_CHANGE event for 127.0.0.1:9042.
+// The server advertises lz4 and snappy compression. When STARTUP asks for either one, every response
+// from READY on is compressed with it, and so are the requests the client flags as compressed.
+
+pub const LATE_RESPONSE_DELAY: Duration = Duration::from_millis(500);
+pub const STALL_LENGTH: usize = 1 << 20;
+pub const TRACED_SESSION: &str = "8e2b9a60-4f3a-11ea-8d77-2e728ce88125";
+
+pub fn start() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || {
+        for socket in listener.incoming() {
+            match socket {
+                Ok(socket) => { thread::spawn(move || serve(socket)); },
+                Err(_) => return
+            }
+        }
+    });
+    port
+}
+
+fn short(buf: &mut Vec<u8>, value: usize) {
+    buf.extend(&[(value >> 8) as u8, value as u8]);
+}
+
+fn int(buf: &mut Vec<u8>, value: usize) {
+    buf.extend(&[(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]);
+}
+
+fn string(buf: &mut Vec<u8>, value: &str) {
+    short(buf, value.len());
+    buf.extend(value.as_bytes());
+}
+
+// Global table spec "ks.t" with the varchar column "v"
+fn column_specs(buf: &mut Vec<u8>) {
+    string(buf, "ks");
+    string(buf, "t");
+    string(buf, "v");
+    short(buf, 0x000D);
+}
+
+fn supported() -> Vec<u8> {
+    let mut body = vec![];
+    short(&mut body, 3);
+    string(&mut body, "PROTOCOL_VERSIONS");
+    short(&mut body, 2);
+    string(&mut body, "3/v3");
+    string(&mut body, "4/v4");
+    string(&mut body, "COMPRESSION");
+    short(&mut body, 2);
+    string(&mut body, "lz4");
+    string(&mut body, "snappy");
+    string(&mut body, "CQL_VERSION");
+    short(&mut body, 1);
+    string(&mut body, "3.4.5");
+    body
+}
+
+fn prepared() -> Vec<u8> {
+    let mut body = vec![];
+    int(&mut body, 4);
+    string(&mut body, "p1");
+    // Bound variables: none, no partition key
+    int(&mut body, 1);
+    int(&mut body, 0);
+    int(&mut body, 0);
+    string(&mut body, "ks");
+    string(&mut body, "t");
+    // Result metadata
+    int(&mut body, 1);
+    int(&mut body, 1);
+    column_specs(&mut body);
+    body
+}
+
+// Rows of ks.t, followed by the paging state of the next page if any
+fn page(values: &[&str], paging_state: Option<&str>) -> Vec<u8> {
+    let mut body = vec![];
+    int(&mut body, 2);
+    int(&mut body, if paging_state.is_some() { 0x0003 } else { 0x0001 });
+    int(&mut body, 1);
+    if let Some(paging_state) = paging_state {
+        int(&mut body, paging_state.len());
+        body.extend(paging_state.as_bytes());
+    }
+    column_specs(&mut body);
+    int(&mut body, values.len());
+    for value in values.iter() {
+        int(&mut body, value.len());
+        body.extend(value.as_bytes());
+    }
+    body
+}
+
+// Paging state of a QUERY without values: [long string] query, [consistency], [byte] flags, then
+// the page size and the paging state if flagged
+fn query_paging_state(body: &[u8], query_len: usize) -> Option<Vec<u8>> {
+    let mut pos = 4 + query_len + 2;
+    let flags = body[pos];
+    pos += 1;
+    if flags & 0x04 != 0 {
+        pos += 4;
+    }
+    if flags & 0x08 == 0 {
+        return None
+    }
+    let len = ((body[pos] as usize) << 24) | ((body[pos + 1] as usize) << 16) | ((body[pos + 2] as usize) << 8) | body[pos + 3] as usize;
+    Some(body[pos + 4 .. pos + 4 + len].to_vec())
+}
+
+fn rows(skip_metadata: bool) -> Vec<u8> {
+    let mut body = vec![];
+    int(&mut body, 2);
+    if skip_metadata {
+        int(&mut body, 4);
+        int(&mut body, 1);
+    } else {
+        int(&mut body, 1);
+        int(&mut body, 1);
+        column_specs(&mut body);
+    }
+    int(&mut body, 1);
+    int(&mut body, 5);
+    body.extend(b"value");
+    body
+}
+
+// One row of `values` in the table system_traces.`table`, or no row at all
+fn trace_rows(table: &str, columns: &[(&str, usize)], values: Option<&[Option<&[u8]>]>) -> Vec<u8> {
+    let mut body = vec![];
+    int(&mut body, 2);
+    int(&mut body, 1);
+    int(&mut body, columns.len());
+    string(&mut body, "system_traces");
+    string(&mut body, table);
+    for &(name, column_type) in columns.iter() {
+        string(&mut body, name);
+        short(&mut body, column_type);
+    }
+    match values {
+        Some(values) => {
+            int(&mut body, 1);
+            for value in values.iter() {
+                match *value {
+                    Some(value) => {
+                        int(&mut body, value.len());
+                        body.extend(value);
+                    },
+                    None => body.extend(&[0xFF, 0xFF, 0xFF, 0xFF])
+                }
+            }
+        },
+        None => int(&mut body, 0)
+    }
+    body
+}
+
+fn trace_session(complete: bool) -> Vec<u8> {
+    let columns = [("coordinator", 0x0010), ("duration", 0x0009), ("request", 0x000D), ("started_at", 0x000B)];
+    let duration: &[u8] = &[0x00, 0x00, 0x01, 0xF4];
+    trace_rows("sessions", &columns, Some(&[Some(&[127, 0, 0, 1]), if complete { Some(duration) } else { None },
+                                            Some(b"Execute CQL3 query"), Some(&[0x00, 0x00, 0x01, 0x6F, 0xDF, 0x56, 0xB8, 0x00])]))
+}
+
+fn trace_events() -> Vec<u8> {
+    let columns = [("event_id", 0x000F), ("activity", 0x000D), ("source", 0x0010), ("source_elapsed", 0x0009), ("thread", 0x000D)];
+    let event_id: &[u8] = &[0x8e, 0x2b, 0xc1, 0x70, 0x4f, 0x3a, 0x11, 0xea, 0x8d, 0x77, 0x2e, 0x72, 0x8c, 0xe8, 0x81, 0x25];
+    trace_rows("events", &columns, Some(&[Some(event_id), Some(b"Parsing query"), Some(&[127, 0, 0, 1]),
+                                          Some(&[0x00, 0x00, 0x00, 0x2A]), Some(b"Native-Transport-Requests-1")]))
+}
+
+fn status_change() -> Vec<u8> {
+    let mut body = vec![];
+    string(&mut body, "STATUS_CHANGE");
+    string(&mut body, "UP");
+    body.extend(&[0x04, 0x7F, 0x00, 0x00, 0x01]);
+    int(&mut body, 9042);
+    body
+}
+
+// Compression requested by STARTUP, if any
+fn startup_compression(body: &[u8]) -> Compression {
+    let mut pos = 2;
+    let mut strings = vec![];
+    while pos < body.len() {
+        let len = ((body[pos] as usize) << 8) | body[pos + 1] as usize;
+        strings.push(&body[pos + 2 .. pos + 2 + len]);
+        pos += 2 + len;
+    }
+    match strings.chunks(2).find(|pair| pair[0] == b"COMPRESSION").map(|pair| pair[1]) {
+        Some(b"lz4") => Compression::Lz4,
+        Some(b"snappy") => Compression::Snappy,
+        _ => Compression::None
+    }
+}
+
+struct Connection {
+    socket: TcpStream,
+    compression: Compression
+}
+
+fn send(connection: &Mutex<Connection>, version: u8, stream: i16, opcode: u8, body: &[u8]) {
+    let mut connection = connection.lock().unwrap();
+    let compressed = connection.compression != Compression::None;
+    let body = if compressed { compress(body, connection.compression).unwrap() } else { body.to_vec() };
+    let mut frame = vec![0x80 | version, compressed as u8, (stream >> 8) as u8, stream as u8, opcode];
+    int(&mut frame, body.len());
+    frame.extend(body);
+    let _ = connection.socket.write_all(&frame);
+}
+
+fn serve(mut socket: TcpStream) {
+    let writer = Arc::new(Mutex::new(Connection { socket: socket.try_clone().unwrap(), compression: Compression::None }));
+    let mut header = [0u8; 9];
+    let mut answered_once = false;
+    let mut session_reads = 0;
+    loop {
+        if socket.read_exact(&mut header).is_err() {
+            return
+        }
+        let version = header[0] & 0x7F;
+        let stream = ((header[2] as i16) << 8) | header[3] as i16;
+        let opcode = header[4];
+        let length = ((header[5] as usize) << 24) | ((header[6] as usize) << 16) | ((header[7] as usize) << 8) | header[8] as usize;
+        if length > STALL_LENGTH {
+            thread::sleep(Duration::from_secs(10));
+            return
+        }
+        let mut body = vec![0u8; length];
+        if socket.read_exact(&mut body).is_err() {
+            return
+        }
+        if header[1] & 0x01 != 0 {
+            body = decompress(&body, writer.lock().unwrap().compression).unwrap();
+        }
+
+        match opcode {
+            // OPTIONS
+            0x05 => send(&writer, version, stream, 0x06, &supported()),
+            // STARTUP
+            0x01 => {
+                writer.lock().unwrap().compression = startup_compression(&body);
+                send(&writer, version, stream, 0x02, &[]);
+            },
+            // REGISTER
+            0x0B => {
+                send(&writer, version, stream, 0x02, &[]);
+                send(&writer, version, -1, 0x0C, &status_change());
+            },
+            // QUERY
+            0x07 => {
+                let query_len = ((body[0] as usize) << 24) | ((body[1] as usize) << 16) | ((body[2] as usize) << 8) | body[3] as usize;
+                let mut query = &body[4 .. 4 + query_len];
+                if query == b"once" {
+                    query = if answered_once { b"void" } else { b"silent" };
+                    answered_once = true;
+                }
+                match query {
+                    b"silent" => {
+                        let writer = writer.clone();
+                        thread::spawn(move || {
+                            thread::sleep(LATE_RESPONSE_DELAY);
+                            let mut late = vec![];
+                            int(&mut late, 3);
+                            string(&mut late, "late");
+                            send(&writer, version, stream, 0x08, &late);
+                        });
+                    },
+                    b"close" => return,
+                    b"pages" => match query_paging_state(&body, query_len) {
+                        None => send(&writer, version, stream, 0x08, &page(&["a", "b"], Some("p2"))),
+                        Some(ref state) if state == b"p2" => send(&writer, version, stream, 0x08, &page(&["c"], None)),
+                        Some(_) => {
+                            let mut error = vec![];
+                            int(&mut error, 0x2200);
+                            string(&mut error, "Invalid paging state");
+                            send(&writer, version, stream, 0x00, &error);
+                        }
+                    },
+                    _ if query.starts_with(b"SELECT coordinator") => {
+                        if query.ends_with(TRACED_SESSION.as_bytes()) {
+                            session_reads += 1;
+                            send(&writer, version, stream, 0x08, &trace_session(session_reads > 1));
+                        } else {
+                            send(&writer, version, stream, 0x08, &trace_rows("sessions", &[], None));
+                        }
+                    },
+                    _ if query.starts_with(b"SELECT event_id") => send(&writer, version, stream, 0x08, &trace_events()),
+                    b"flags" => {
+                        let mut keyspace = vec![];
+                        int(&mut keyspace, 3);
+                        string(&mut keyspace, &body[4 + query_len + 2].to_string());
+                        send(&writer, version, stream, 0x08, &keyspace);
+                    },
+                    _ => send(&writer, version, stream, 0x08, &[0x00, 0x00, 0x00, 0x01])
+                }
+            },
+            // PREPARE
+            0x09 => send(&writer, version, stream, 0x08, &prepared()),
+            // EXECUTE: [short bytes] id, [consistency], then the query flags
+            0x0A => {
+                let id_len = ((body[0] as usize) << 8) | body[1] as usize;
+                let flags = body[id_len + 4];
+                send(&writer, version, stream, 0x08, &rows(flags & 0x02 != 0));
+            },
+            // Unsupported: protocol error
+            _ => {
+                let mut error = vec![];
+                int(&mut error, 0x000A);
+                string(&mut error, "Unsupported request");
+                send(&writer, version, stream, 0x00, &error);
+            }
+        }
+    }
+}
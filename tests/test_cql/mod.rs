@@ -1,17 +1,36 @@
 extern crate std;
 
 use std::borrow::Cow;
-use std::io::Write;
-use cql;
 
-pub fn to_hex_string(bytes: &Vec<u8>) -> String {
+mod segment;
+mod compression;
+mod response;
+mod request;
+mod negotiation;
+mod event;
+mod decimal;
+mod udt;
+mod collection;
+mod duration;
+mod datetime;
+mod timestamp;
+mod timeout;
+mod trace;
+mod stream;
+mod paging;
+mod fake_server;
+#[cfg(feature = "async")]
+mod async_client;
+
+pub fn to_hex_string(bytes: &[u8]) -> String {
   let strs: Vec<String> = bytes.iter()
                                .map(|b| format!("{:02X}", b))
                                .collect();
-  strs.connect(" ")
+  strs.join(" ")
 }
 
 #[test]
+#[ignore = "needs a Cassandra node at 127.0.0.1:9042, run with --ignored"]
 fn test() {
     println!("Connecting ...!");
     let creds = vec![Cow::Borrowed("cassandra"), Cow::Borrowed("cassandra")];
@@ -20,74 +39,336 @@ fn test() {
 
     let mut q = "create keyspace if not exists rust with replication = {'class': 'SimpleStrategy', 'replication_factor':1}";
     println!("cql::Query: {}", q);
-    let mut response = try_test!(client.exec_query(q, cql::Consistency::One), "Error creating keyspace");
+    let mut response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error creating keyspace");
     assert_response!(response);
     println!("Result: {:?} \n", response);
 
     q = "create table if not exists rust.test (id text primary key, f32 float, f64 double, i32 int, i64 bigint, b boolean, ip inet)";
     println!("cql::Query: {}", q);
-    response = try_test!(client.exec_query(q, cql::Consistency::One), "Error creating table rust.test");
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error creating table rust.test");
     assert_response!(response);
     println!("Result: {:?} \n", response);
 
+    q = "create table rust.test (id text primary key)";
+    println!("cql::Query (already exists): {}", q);
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error creating table rust.test again");
+    match response.body {
+        cql::CqlResponseBody::ResponseError(cql::ServerError::AlreadyExists { ref keyspace, ref table }, _) => {
+            assert_eq!(keyspace, "rust");
+            assert_eq!(table, "test");
+        },
+        ref body => panic!("Unexpected response {:?}", body)
+    }
+
     q = "insert into rust.test (id, f32, f64, i32, i64, b, ip) values ('asdf', 1.2345, 3.14159, 47, 59, true, '127.0.0.1')";
     println!("cql::Query: {}", q);
-    response = try_test!(client.exec_query(q, cql::Consistency::One), "Error inserting into table test");
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error inserting into table test");
     assert_response!(response);
     println!("Result: {:?} \n", response);
 
     q = "select * from rust.test";
     println!("cql::Query: {}", q);
-    response = try_test!(client.exec_query(q, cql::Consistency::One), "Error selecting from table test");
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error selecting from table test");
     assert_response!(response);
     println!("Result: {:?} \n", response);
 
     q = "insert into rust.test (id, f32) values (?, ?)";
+    println!("cql::Query (with values): {}", q);
+    let values: &[cql::CqlValue] = &[cql::CqlVarchar(Some(Cow::Borrowed("bound"))), cql::CqlFloat(Some(2.5))];
+    response = try_test!(client.exec_query(q, values, cql::Consistency::One), "Error inserting into table test with values");
+    assert_response!(response);
+    println!("Result: {:?} \n", response);
+
     println!("Create prepared: {}", q);
     let preps = try_test!(client.prepared_statement(q), "Error creating prepared statement");
     println!("Created prepared with id = {}", to_hex_string(&preps.id));
 
     println!("Execute prepared");
     let params: &[cql::CqlValue] = &[cql::CqlVarchar(Some(Cow::Borrowed("ttrwe"))), cql::CqlFloat(Some(15.1617))];
-    response = try_test!(client.exec_prepared(&preps.id, params, cql::Consistency::One), "Error executing prepared statement");
+    response = try_test!(client.exec_prepared(&preps, params, cql::Consistency::One), "Error executing prepared statement");
+    assert_response!(response);
+    println!("Result: {:?} \n", response);
+
+    println!("Execute prepared (named values)");
+    let named: &[(&str, cql::CqlValue)] = &[("f32", cql::CqlFloat(Some(7.5))), ("id", cql::CqlVarchar(Some(Cow::Borrowed("named"))))];
+    response = try_test!(client.exec_prepared_named(&preps, named, cql::Consistency::One), "Error executing prepared statement with named values");
     assert_response!(response);
     println!("Result: {:?} \n", response);
 
     q = "select * from rust.test";
     println!("cql::Query: {}", q);
-    response = try_test!(client.exec_query(q, cql::Consistency::One), "Error selecting from table test");
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error selecting from table test");
     assert_response!(response);
     println!("Result: {:?} \n", response);
 
+    println!("Execute prepared select (skip metadata)");
+    let select = try_test!(client.prepared_statement("select id, f32 from rust.test where id = ?"), "Error creating prepared select");
+    client.skip_metadata = true;
+    let key: &[cql::CqlValue] = &[cql::CqlVarchar(Some(Cow::Borrowed("ttrwe")))];
+    response = try_test!(client.exec_prepared(&select, key, cql::Consistency::One), "Error executing prepared select");
+    client.skip_metadata = false;
+    assert_response!(response);
+    match response.body {
+        cql::CqlResponseBody::ResultRows(ref rows) => assert_eq!(rows.rows[0].cols.len(), 2),
+        _ => panic!("Prepared select did not return rows")
+    }
+    println!("Result: {:?} \n", response);
+
+    q = "insert into rust.test (id, f32) values ('lwt', 1.0) if not exists";
+    println!("cql::Query (conditional): {}", q);
+    client.serial_consistency = Some(cql::Consistency::LocalSerial);
+    try_test!(client.exec_query(q, &[], cql::Consistency::LocalOne), "Error inserting into table test (conditional)");
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::LocalOne), "Error inserting into table test (conditional)");
+    client.serial_consistency = None;
+    let lwt = try_test!(response.lwt_result(), "Error reading conditional update result");
+    assert!(!lwt.applied);
+    println!("Result: {:?} \n", lwt);
+
+    q = "insert into rust.test (id, i64) values ('timestamped', 1)";
+    println!("cql::Query (timestamp): {}", q);
+    let mut options = cql::QueryOptions::new(cql::Consistency::One);
+    options.timestamp = Some(1234567890);
+    response = try_test!(client.exec_query(q, &[], options), "Error inserting into table test with timestamp");
+    assert_response!(response);
+    response = try_test!(client.exec_query("select writetime(i64) from rust.test where id = 'timestamped'", &[], cql::Consistency::One), "Error selecting writetime");
+    match response.body {
+        cql::CqlResponseBody::ResultRows(ref rows) => match rows.rows[0].cols[0] {
+            cql::CqlValue::CqlBigInt(Some(t)) => assert_eq!(t, 1234567890),
+            ref col => panic!("Unexpected writetime {:?}", col)
+        },
+        _ => panic!("Select writetime did not return rows")
+    }
+
     println!("Execute batch");
     let params2: Vec<cql::CqlValue> = vec![cql::CqlVarchar(Some(Cow::Borrowed("batch2"))), cql::CqlFloat(Some(666.65))];
     let q_vec = vec![cql::QueryStr(Cow::Borrowed("insert into rust.test (id, f32) values ('batch1', 34.56)")),
-                     cql::QueryPrepared(preps.id, params2)];
+                     cql::QueryPrepared(preps.id.clone(), params2)];
     response = try_test!(client.exec_batch(cql::BatchType::Logged, q_vec, cql::Consistency::One), "Error executing batch cql::Query");
     assert_response!(response);
     println!("Result: {:?} \n", response);
 
     q = "select * from rust.test";
     println!("cql::Query: {}", q);
-    response = try_test!(client.exec_query(q, cql::Consistency::One), "Error selecting from table test");
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error selecting from table test");
+    assert_response!(response);
+    println!("Result: {:?} \n", response);
+
+    println!("Execute batch (values and timestamp)");
+    let q_vec = vec![cql::QueryStrValues(Cow::Borrowed("insert into rust.test (id, f32) values (?, ?)"),
+                                         vec![cql::CqlVarchar(Some(Cow::Borrowed("batch3"))), cql::CqlFloat(Some(1.25))])];
+    let mut options = cql::QueryOptions::new(cql::Consistency::One);
+    options.timestamp = Some(1234567890);
+    response = try_test!(client.exec_batch(cql::BatchType::Logged, q_vec, options), "Error executing batch with values");
+    assert_response!(response);
+    let q_vec = vec![cql::QueryPrepared(preps.id.clone(), vec![cql::CqlVarchar(Some(Cow::Borrowed("batch4"))), cql::CqlFloat(Some(2.25))])];
+    response = try_test!(client.exec_batch(cql::BatchType::Logged, q_vec, cql::Consistency::One), "Error executing batch with prepared values");
     assert_response!(response);
     println!("Result: {:?} \n", response);
 
     q = "create table if not exists rust.test2 (id text primary key, l list<int>, m map<int, text>, s set<float>)";
     println!("cql::Query: {}", q);
-    response = try_test!(client.exec_query(q, cql::Consistency::One), "Error creating table test2");
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error creating table test2");
     assert_response!(response);
     println!("Result: {:?} \n", response);
 
     q = "insert into rust.test2 (id, l, m, s) values ('asdf', [1,2,3,4,5], {0: 'aa', 1: 'bbb', 3: 'cccc'}, {1.234, 2.345, 3.456, 4.567})";
     println!("cql::Query: {}", q);
-    response = try_test!(client.exec_query(q, cql::Consistency::One), "Error inserting into table test2");
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error inserting into table test2");
     assert_response!(response);
     println!("Result: {:?} \n", response);
 
     q = "select * from rust.test2";
     println!("cql::Query: {}", q);
-    response = try_test!(client.exec_query(q, cql::Consistency::One), "Error selecting from table test2");
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error selecting from table test2");
+    assert_response!(response);
+    println!("Result: {:?} \n", response);
+
+    q = "create table if not exists rust.numbers (id text primary key, d decimal, v varint)";
+    println!("cql::Query: {}", q);
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error creating table numbers");
+    assert_response!(response);
+
+    q = "insert into rust.numbers (id, d, v) values (?, ?, ?)";
+    println!("cql::Query (decimal and varint): {}", q);
+    let decimal: cql::Decimal = "-1234567890123456789.0125".parse().unwrap();
+    let varint: num::BigInt = "-98765432109876543210987654321".parse().unwrap();
+    let values: &[cql::CqlValue] = &[cql::CqlVarchar(Some(Cow::Borrowed("big"))),
+                                     cql::CqlValue::CqlDecimal(Some(decimal.clone())),
+                                     cql::CqlValue::CqlVarint(Some(varint.clone()))];
+    response = try_test!(client.exec_query(q, values, cql::Consistency::One), "Error inserting into table numbers");
+    assert_response!(response);
+    response = try_test!(client.exec_query("select d, v from rust.numbers where id = 'big'", &[], cql::Consistency::One), "Error selecting from table numbers");
+    match response.body {
+        cql::CqlResponseBody::ResultRows(ref rows) => {
+            match rows.rows[0].cols[0] {
+                cql::CqlValue::CqlDecimal(Some(ref d)) => assert_eq!(*d, decimal),
+                ref v => panic!("Unexpected decimal {:?}", v)
+            }
+            match rows.rows[0].cols[1] {
+                cql::CqlValue::CqlVarint(Some(ref v)) => assert_eq!(*v, varint),
+                ref v => panic!("Unexpected varint {:?}", v)
+            }
+        },
+        ref body => panic!("Unexpected response {:?}", body)
+    }
+    println!("Result: {:?} \n", response);
+
+    q = "create table if not exists rust.durations (id text primary key, d duration)";
+    println!("cql::Query: {}", q);
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error creating table durations");
+    assert_response!(response);
+
+    let duration: cql::Duration = "1h30m".parse().unwrap();
+    q = "insert into rust.durations (id, d) values ('bound', ?)";
+    println!("cql::Query (duration): {}", q);
+    response = try_test!(client.exec_query(q, &[cql::CqlValue::CqlDuration(Some(duration))], cql::Consistency::One), "Error inserting into table durations");
+    assert_response!(response);
+    let literal = format!("insert into rust.durations (id, d) values ('literal', {})", cql::Duration::new(-14, -3, -5));
+    println!("cql::Query: {}", literal);
+    response = try_test!(client.exec_query(&literal, &[], cql::Consistency::One), "Error inserting into table durations");
+    assert_response!(response);
+    response = try_test!(client.exec_query("select id, d from rust.durations", &[], cql::Consistency::One), "Error selecting from table durations");
+    match response.body {
+        cql::CqlResponseBody::ResultRows(ref rows) => {
+            assert_eq!(rows.rows.len(), 2);
+            for row in rows.rows.iter() {
+                match (&row.cols[0], &row.cols[1]) {
+                    (&cql::CqlVarchar(Some(ref id)), &cql::CqlValue::CqlDuration(Some(d))) if id == "bound" => assert_eq!(d, duration),
+                    (&cql::CqlVarchar(Some(_)), &cql::CqlValue::CqlDuration(Some(d))) => assert_eq!(d, cql::Duration::new(-14, -3, -5)),
+                    v => panic!("Unexpected duration {:?}", v)
+                }
+            }
+        },
+        ref body => panic!("Unexpected response {:?}", body)
+    }
+    println!("Result: {:?} \n", response);
+
+    q = "create type if not exists rust.address (street text, number int)";
+    println!("cql::Query: {}", q);
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error creating type address");
+    assert_response!(response);
+
+    q = "create table if not exists rust.udts (id text primary key, a frozen<address>)";
+    println!("cql::Query: {}", q);
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error creating table udts");
+    assert_response!(response);
+
+    q = "insert into rust.udts (id, a) values (?, ?)";
+    println!("cql::Query (UDT): {}", q);
+    let address = vec![(Cow::Borrowed("street"), cql::CqlVarchar(Some(Cow::Borrowed("Main St")))),
+                       (Cow::Borrowed("number"), cql::CqlValue::CqlInt(Some(42)))];
+    let values: &[cql::CqlValue] = &[cql::CqlVarchar(Some(Cow::Borrowed("home"))), cql::CqlValue::CqlUdt(Some(address))];
+    response = try_test!(client.exec_query(q, values, cql::Consistency::One), "Error inserting into table udts");
+    assert_response!(response);
+    response = try_test!(client.exec_query("select a from rust.udts where id = 'home'", &[], cql::Consistency::One), "Error selecting from table udts");
+    match response.body {
+        cql::CqlResponseBody::ResultRows(ref rows) => {
+            match rows.metadata.row_metadata[0].col_type_info {
+                cql::CqlType::Udt(ref udt) => assert_eq!(udt.name, "address"),
+                ref t => panic!("Unexpected type {:?}", t)
+            }
+            match rows.rows[0].cols[0] {
+                cql::CqlValue::CqlUdt(Some(ref fields)) => {
+                    assert_eq!(fields[0].0, "street");
+                    assert_eq!(fields[1].0, "number");
+                    match fields[1].1 {
+                        cql::CqlValue::CqlInt(Some(n)) => assert_eq!(n, 42),
+                        ref v => panic!("Unexpected field {:?}", v)
+                    }
+                },
+                ref v => panic!("Unexpected UDT {:?}", v)
+            }
+        },
+        ref body => panic!("Unexpected response {:?}", body)
+    }
+    println!("Result: {:?} \n", response);
+
+    q = "create table if not exists rust.tuples (id text primary key, t tuple<int, text>, l list<frozen<tuple<int, text>>>)";
+    println!("cql::Query: {}", q);
+    response = try_test!(client.exec_query(q, &[], cql::Consistency::One), "Error creating table tuples");
+    assert_response!(response);
+
+    q = "insert into rust.tuples (id, t, l) values (?, ?, ?)";
+    println!("cql::Query (tuples): {}", q);
+    let values: &[cql::CqlValue] = &[cql::CqlVarchar(Some(Cow::Borrowed("pair"))),
+                                     cql::CqlValue::CqlTuple(Some(vec![cql::CqlValue::CqlInt(Some(1)), cql::CqlVarchar(None)])),
+                                     cql::CqlValue::CqlList(Some(vec![cql::CqlValue::CqlTuple(Some(vec![cql::CqlValue::CqlInt(Some(2)),
+                                                                                                     cql::CqlVarchar(Some(Cow::Borrowed("two")))]))]))];
+    response = try_test!(client.exec_query(q, values, cql::Consistency::One), "Error inserting into table tuples");
+    assert_response!(response);
+    response = try_test!(client.exec_query("select t, l from rust.tuples where id = 'pair'", &[], cql::Consistency::One), "Error selecting from table tuples");
+    match response.body {
+        cql::CqlResponseBody::ResultRows(ref rows) => {
+            match rows.rows[0].cols[0] {
+                cql::CqlValue::CqlTuple(Some(ref t)) => {
+                    assert_eq!(t.len(), 2);
+                    match t[1] {
+                        cql::CqlVarchar(None) => (),
+                        ref v => panic!("Unexpected tuple component {:?}", v)
+                    }
+                },
+                ref v => panic!("Unexpected tuple {:?}", v)
+            }
+            match rows.rows[0].cols[1] {
+                cql::CqlValue::CqlList(Some(ref l)) => assert_eq!(l.len(), 1),
+                ref v => panic!("Unexpected list {:?}", v)
+            }
+        },
+        ref body => panic!("Unexpected response {:?}", body)
+    }
+    println!("Result: {:?} \n", response);
+
+    println!("Paged query");
+    let rows: Vec<cql::CqlRow> = client.query_iter("select * from rust.test", &[], cql::Consistency::One, 1)
+                                       .map(|row| try_test!(row, "Error fetching page"))
+                                       .collect();
+    assert!(rows.len() > 1);
+    println!("Rows: {:?} \n", rows);
+
+    println!("Query with options");
+    let mut options = cql::QueryOptions::new(cql::Consistency::One);
+    options.page_size = Some(1);
+    options.timeout = Some(std::time::Duration::from_secs(10));
+    if client.version >= 5 {
+        options.keyspace = Some(Cow::Borrowed("rust"));
+    }
+    response = try_test!(client.exec_query("select * from rust.test", &[], options), "Error selecting from table test with options");
+    assert_response!(response);
+    println!("Result: {:?} \n", response);
+
+    println!("Pipelined queries");
+    let stream1 = try_test!(client.send_query("select * from rust.test", &[], cql::Consistency::One), "Error sending query on first stream");
+    let stream2 = try_test!(client.send_query("select * from rust.test2", &[], cql::Consistency::One), "Error sending query on second stream");
+    assert!(stream1 != stream2);
+    response = try_test!(client.get_response(stream2), "Error reading response on second stream");
+    assert_response!(response);
+    response = try_test!(client.get_response(stream1), "Error reading response on first stream");
+    assert_response!(response);
+    println!("Result: {:?} \n", response);
+
+    q = "select * from rust.test";
+    println!("cql::Query (traced): {}", q);
+    let mut options = cql::QueryOptions::new(cql::Consistency::One);
+    options.tracing = true;
+    response = try_test!(client.exec_query(q, &[], options), "Error selecting from table test with tracing");
+    assert_response!(response);
+    let session_id = response.tracing_id.expect("Traced response without tracing id");
+    let session = try_test!(client.get_trace_session(&session_id), "Error fetching trace session");
+    println!("Trace session: {:?} \n", session);
+
+    println!("Connecting with LZ4 compression ...");
+    let mut client = try_test!(cql::connect_with_compression("127.0.0.1", 9042, Some(&creds), &[cql::Compression::Lz4]), "Error connecting with LZ4 compression");
+    assert_eq!(client.compression, cql::Compression::Lz4);
+    response = try_test!(client.exec_query("select * from rust.test", &[], cql::Consistency::One), "Error selecting from table test with LZ4 compression");
+    assert_response!(response);
+    println!("Result: {:?} \n", response);
+
+    // Snappy is not available from protocol v5 on, where the client falls back to no compression
+    println!("Connecting with Snappy compression ...");
+    let mut client = try_test!(cql::connect_with_compression("127.0.0.1", 9042, Some(&creds), &[cql::Compression::Snappy]), "Error connecting with Snappy compression");
+    let expected = if client.version >= 5 { cql::Compression::None } else { cql::Compression::Snappy };
+    assert_eq!(client.compression, expected);
+    response = try_test!(client.exec_query("select * from rust.test", &[], cql::Consistency::One), "Error selecting from table test with Snappy compression");
     assert_response!(response);
     println!("Result: {:?} \n", response);
 }
@@ -0,0 +1,85 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use cql::Compression;
+use cql::client::*;
+
+fn supported(options: &[(&'static str, &[&'static str])]) -> BTreeMap<Cow<'static, str>, Vec<Cow<'static, str>>> {
+    options.iter().map(|&(k, v)| (Cow::Borrowed(k), v.iter().map(|s| Cow::Borrowed(*s)).collect())).collect()
+}
+
+#[test]
+fn test_choose_protocol_version() {
+    let cases: &[(&[&'static str], u8, Option<u8>)] = &[
+        (&["3/v3", "4/v4", "5/v5"], 5, Some(5)),
+        (&["3/v3", "4/v4", "5/v5"], 4, Some(4)),
+        (&["3/v3", "4/v4", "5/v5-beta"], 5, Some(4)),
+        (&["5/v5-beta"], 5, None),
+        (&["3/v3", "4/v4"], 2, None),
+        (&["junk", "4/v4"], 5, Some(4)),
+        (&[], 5, None),
+    ];
+    for &(versions, max_version, expected) in cases.iter() {
+        let options = supported(&[("PROTOCOL_VERSIONS", versions)]);
+        assert_eq!(choose_protocol_version(&options, max_version), expected, "{:?} (max v{})", versions, max_version);
+    }
+    // Older servers do not advertise their protocol versions
+    assert_eq!(choose_protocol_version(&supported(&[]), 5), None);
+}
+
+#[test]
+fn test_choose_cql_version() {
+    let cases: &[(&[&'static str], &str)] = &[
+        (&["3.4.5"], "3.4.5"),
+        (&["3.4.5", "3.10.0", "3.4.10"], "3.10.0"),
+        (&["3.0.0", "3.0"], "3.0.0"),
+        (&[], "3.0.0"),
+    ];
+    for &(versions, expected) in cases.iter() {
+        let options = supported(&[("CQL_VERSION", versions)]);
+        assert_eq!(choose_cql_version(&options), expected, "{:?}", versions);
+    }
+    assert_eq!(choose_cql_version(&supported(&[])), CQL_DEFAULT_VERSION);
+}
+
+#[test]
+fn test_choose_compression() {
+    let both = [Compression::Lz4, Compression::Snappy];
+    let cases: &[(&[&'static str], &[Compression], u8, Compression)] = &[
+        (&["snappy", "lz4"], &both, 4, Compression::Lz4),
+        (&["snappy", "lz4"], &[Compression::Snappy, Compression::Lz4], 4, Compression::Snappy),
+        (&["snappy"], &both, 4, Compression::Snappy),
+        (&["lz4"], &[Compression::Snappy], 4, Compression::None),
+        (&["snappy", "lz4"], &[], 4, Compression::None),
+        (&[], &both, 4, Compression::None),
+        // Only LZ4 on v5
+        (&["snappy", "lz4"], &[Compression::Snappy, Compression::Lz4], 5, Compression::Lz4),
+        (&["snappy"], &both, 5, Compression::None),
+    ];
+    for &(algorithms, preferred, version, expected) in cases.iter() {
+        let options = supported(&[("COMPRESSION", algorithms)]);
+        assert_eq!(choose_compression(&options, preferred, version), expected, "{:?} {:?} v{}", algorithms, preferred, version);
+    }
+    assert_eq!(choose_compression(&supported(&[]), &both, 4), Compression::None);
+}
+
+#[test]
+fn test_no_suitable_protocol_version() {
+    // Closes every connection right away, so that each protocol version fails
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for socket in listener.incoming() {
+            drop(socket);
+        }
+    });
+
+    match cql::connect("127.0.0.1", port, None) {
+        Ok(_) => panic!("Connected to a server that closes every connection"),
+        Err(err) => {
+            assert!(err.desc.contains("Unable to find suitable protocol version"), "{}", err.desc);
+            for version in ["v5: ", "v4: ", "v3: ", "v2: ", "v1: "].iter() {
+                assert!(err.desc.contains(version), "{}", err.desc);
+            }
+        }
+    }
+}
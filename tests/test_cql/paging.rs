@@ -0,0 +1,53 @@
+use cql::*;
+use super::fake_server;
+
+fn values(rows: &[CqlRow]) -> Vec<String> {
+    rows.iter().map(|row| match row.cols[..] {
+        [CqlVarchar(Some(ref value))] => value.to_string(),
+        ref cols => panic!("Unexpected columns {:?}", cols)
+    }).collect()
+}
+
+#[test]
+fn test_query_pages() {
+    let port = fake_server::start();
+    let mut client = try_test!(connect("127.0.0.1", port, None), "Error connecting to the fake server");
+
+    let mut options = QueryOptions::new(Consistency::One);
+    options.page_size = Some(2);
+    let response = try_test!(client.exec_query("pages", &[], options.clone()), "Error fetching first page");
+    let paging_state = match response.body {
+        CqlResponseBody::ResultRows(rows) => {
+            assert!(rows.has_more_pages());
+            assert_eq!(values(&rows.rows), vec!["a", "b"]);
+            rows.metadata.paging_state.unwrap()
+        },
+        body => panic!("Unexpected body {:?}", body)
+    };
+    assert_eq!(paging_state, b"p2".to_vec());
+
+    options.paging_state = Some(paging_state);
+    let response = try_test!(client.exec_query("pages", &[], options), "Error fetching second page");
+    match response.body {
+        CqlResponseBody::ResultRows(rows) => {
+            assert!(!rows.has_more_pages());
+            assert_eq!(values(&rows.rows), vec!["c"]);
+        },
+        body => panic!("Unexpected body {:?}", body)
+    }
+}
+
+#[test]
+fn test_row_iter() {
+    let port = fake_server::start();
+    let mut client = try_test!(connect("127.0.0.1", port, None), "Error connecting to the fake server");
+
+    let rows: RCResult<Vec<CqlRow>> = client.query_iter("pages", &[], Consistency::One, 2).collect();
+    let rows = try_test!(rows, "Error iterating over the pages");
+    assert_eq!(values(&rows), vec!["a", "b", "c"]);
+
+    // A failed page ends the iteration
+    let mut rows = client.query_iter("select v from ks.t", &[], Consistency::One, 2);
+    assert!(rows.next().unwrap().is_err());
+    assert!(rows.next().is_none());
+}
@@ -0,0 +1,228 @@
+use cql::*;
+use cql::serialize::*;
+
+fn serialize_params(params: &QueryParams, version: u8) -> RCResult<Vec<u8>> {
+    let mut buf = vec![];
+    params.serialize(&mut buf, version).map(|_| buf)
+}
+
+fn serialize_batch_params(params: &BatchParams, version: u8) -> RCResult<Vec<u8>> {
+    let mut buf = vec![];
+    params.serialize(&mut buf, version).map(|_| buf)
+}
+
+fn serialize_request(request: &CqlRequest, version: u8) -> RCResult<Vec<u8>> {
+    let mut buf = vec![];
+    request.serialize(&mut buf, version).map(|_| buf)
+}
+
+fn query<'a>(query_str: &'a str, params: QueryParams<'a>) -> CqlRequest<'a> {
+    CqlRequest { flags: 0x00, stream: 0x01, opcode: OpcodeRequest::OpcodeQuery, body: CqlRequestBody::RequestQuery(query_str, params) }
+}
+
+fn metadata() -> CqlMetadata {
+    CqlMetadata { flags: 0x0001, column_count: 0, keyspace: "ks".into(), table: "t".into(), pk_indices: vec![],
+                  paging_state: None, new_metadata_id: None, row_metadata: vec![] }
+}
+
+// [long string] "q", the consistency ONE, the values flag and the values 1 (int) and "ab" (varchar)
+static QUERY_VALUES_BODY: [u8; 24] = [0x00, 0x00, 0x00, 0x01, 0x71,
+                                      0x00, 0x01, 0x01, 0x00, 0x02,
+                                      0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01,
+                                      0x00, 0x00, 0x00, 0x02, 0x61, 0x62];
+
+#[test]
+fn test_unset_value() {
+    let values = [CqlValue::CqlInt(Some(1)), CqlValue::CqlUnset];
+    let params = QueryParams::new(Consistency::One, &values);
+
+    // consistency, flags, 2 values: the int 1 and the unset marker (length -2)
+    assert_eq!(serialize_params(&params, 4).unwrap(),
+               vec![0x00, 0x01, 0x01, 0x00, 0x02,
+                    0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01,
+                    0xFF, 0xFF, 0xFF, 0xFE]);
+
+    // Unset values were introduced in v4
+    assert!(serialize_params(&params, 3).is_err());
+    assert!(CqlValue::CqlUnset.serialize(&mut vec![], 3).is_err());
+}
+
+#[test]
+fn test_query_values() {
+    let values = [CqlValue::CqlInt(Some(1)), CqlValue::CqlVarchar(Some("ab".into()))];
+
+    // v2 header: the stream is a single byte
+    let mut expected = vec![0x02, 0x00, 0x01, 0x07, 0x00, 0x00, 0x00, 0x18];
+    expected.extend(&QUERY_VALUES_BODY);
+    assert_eq!(serialize_request(&query("q", QueryParams::new(Consistency::One, &values)), 2).unwrap(), expected);
+
+    // v4 header: the stream is a short
+    let mut expected = vec![0x04, 0x00, 0x00, 0x01, 0x07, 0x00, 0x00, 0x00, 0x18];
+    expected.extend(&QUERY_VALUES_BODY);
+    assert_eq!(serialize_request(&query("q", QueryParams::new(Consistency::One, &values)), 4).unwrap(), expected);
+
+    // v1 has no query flags, so a query cannot carry values
+    assert_eq!(serialize_request(&query("q", QueryParams::new(Consistency::One, &[])), 1).unwrap(),
+               vec![0x01, 0x00, 0x01, 0x07, 0x00, 0x00, 0x00, 0x07,
+                    0x00, 0x00, 0x00, 0x01, 0x71, 0x00, 0x01]);
+    match serialize_request(&query("q", QueryParams::new(Consistency::One, &values)), 1) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::SerializeError),
+        Ok(data) => panic!("Serialized bind values on v1: {:?}", data)
+    }
+}
+
+#[test]
+fn test_query_params_len() {
+    let values = [CqlValue::CqlInt(Some(1)), CqlValue::CqlVarchar(Some("ab".into())), CqlValue::CqlBigInt(None)];
+    let named_values = [("k", CqlValue::CqlInt(Some(1)))];
+    let mut all = QueryParams::new(Consistency::Quorum, &values);
+    all.page_size = Some(100);
+    all.paging_state = Some(vec![0x01, 0x02, 0x03]);
+    all.serial_consistency = Some(Consistency::LocalSerial);
+    all.timestamp = Some(1234);
+    let mut with_keyspace = all.clone();
+    with_keyspace.keyspace = Some("ks".into());
+    let mut named = QueryParams::new(Consistency::One, &[]);
+    named.named_values = &named_values;
+
+    let cases = [(QueryParams::new(Consistency::One, &[]), 1u8), (QueryParams::new(Consistency::One, &values), 2),
+                 (all.clone(), 3), (all, 4), (with_keyspace, 5), (named.clone(), 4), (named, 5)];
+    for &(ref params, version) in cases.iter() {
+        let data = serialize_params(params, version).unwrap();
+        assert_eq!(params.len(version), data.len(), "v{}", version);
+        let request = query("select", params.clone());
+        assert_eq!(request.len(version), serialize_request(&request, version).unwrap().len(), "v{}", version);
+    }
+}
+
+#[test]
+fn test_named_values() {
+    let named_values = [("k", CqlValue::CqlInt(Some(1))), ("v", CqlValue::CqlVarchar(None))];
+    let mut params = QueryParams::new(Consistency::One, &[]);
+    params.named_values = &named_values;
+
+    // consistency, values and names flags, 2 values: "k" = 1 and "v" = null
+    assert_eq!(serialize_params(&params, 4).unwrap(),
+               vec![0x00, 0x01, 0x41, 0x00, 0x02,
+                    0x00, 0x01, 0x6B, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01,
+                    0x00, 0x01, 0x76, 0xFF, 0xFF, 0xFF, 0xFF]);
+    // The flags are an [int] from v5 on
+    assert_eq!(serialize_params(&params, 5).unwrap()[.. 7].to_vec(), vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x41, 0x00]);
+
+    // Named values were introduced in v3, v1 has no query flags at all
+    for &version in [1u8, 2].iter() {
+        match serialize_request(&query("q", params.clone()), version) {
+            Err(err) => assert_eq!(err.kind, RCErrorType::SerializeError),
+            Ok(data) => panic!("Serialized named values on v{}: {:?}", version, data)
+        }
+    }
+
+    let preps = CqlPreparedStat { id: vec![0x70, 0x31], result_metadata_id: None, meta: metadata(), meta_result: None };
+    let execute = CqlRequest { flags: 0x00, stream: 0x01, opcode: OpcodeRequest::OpcodeExecute,
+                               body: CqlRequestBody::RequestExec(&preps, params.clone()) };
+    assert!(serialize_request(&execute, 1).is_err());
+
+    // Positional and named values cannot be mixed
+    let values = [CqlValue::CqlInt(Some(1))];
+    params.values = &values;
+    assert!(serialize_params(&params, 4).is_err());
+}
+
+fn batch(params: BatchParams) -> CqlRequest<'static> {
+    let queries = vec![QueryStrValues("insert ?".into(), vec![CqlValue::CqlInt(Some(1))]),
+                       QueryPrepared(vec![0x70, 0x31], vec![CqlValue::CqlVarchar(Some("a".into()))]),
+                       QueryStr("delete".into())];
+    CqlRequest { flags: 0x00, stream: 0x01, opcode: OpcodeRequest::OpcodeBatch,
+                 body: CqlRequestBody::RequestBatch(queries, BatchType::Logged, params) }
+}
+
+// LOGGED batch of 3 statements: a string with values, a prepared one with values and a string without values
+static BATCH_BODY: [u8; 51] = [0x00, 0x00, 0x03,
+                               // kind 0, [long string] "insert ?", 1 value: 1 (int)
+                               0x00, 0x00, 0x00, 0x00, 0x08, 0x69, 0x6E, 0x73, 0x65, 0x72, 0x74, 0x20, 0x3F,
+                               0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01,
+                               // kind 1, [short bytes] id "p1", 1 value: "a" (varchar)
+                               0x01, 0x00, 0x02, 0x70, 0x31, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x61,
+                               // kind 0, [long string] "delete", no values
+                               0x00, 0x00, 0x00, 0x00, 0x06, 0x64, 0x65, 0x6C, 0x65, 0x74, 0x65, 0x00, 0x00];
+
+#[test]
+fn test_batch() {
+    let mut params = BatchParams::new(Consistency::One);
+
+    // v2 batches end with the consistency only
+    let request = batch(params.clone());
+    let data = serialize_request(&request, 2).unwrap();
+    assert_eq!(data[.. 8].to_vec(), vec![0x02, 0x00, 0x01, 0x0D, 0x00, 0x00, 0x00, 0x35]);
+    assert_eq!(data[8 .. 59].to_vec(), BATCH_BODY.to_vec());
+    assert_eq!(data[59 ..].to_vec(), vec![0x00, 0x01]);
+    assert_eq!(request.len(2), data.len());
+
+    // From v3 on: consistency, flags, then the serial consistency and the timestamp
+    params.serial_consistency = Some(Consistency::LocalSerial);
+    params.timestamp = Some(1);
+    let request = batch(params.clone());
+    let data = serialize_request(&request, 4).unwrap();
+    assert_eq!(data[.. 9].to_vec(), vec![0x04, 0x00, 0x00, 0x01, 0x0D, 0x00, 0x00, 0x00, 0x40]);
+    assert_eq!(data[9 .. 60].to_vec(), BATCH_BODY.to_vec());
+    assert_eq!(data[60 ..].to_vec(), vec![0x00, 0x01, 0x30, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]);
+    assert_eq!(request.len(4), data.len());
+
+    // v5 flags are an [int], and the keyspace follows the timestamp
+    params.keyspace = Some("ks".into());
+    let request = batch(params.clone());
+    let data = serialize_request(&request, 5).unwrap();
+    assert_eq!(data[60 ..].to_vec(), vec![0x00, 0x01, 0x00, 0x00, 0x00, 0xB0, 0x00, 0x09,
+                                          0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x6B, 0x73]);
+    assert_eq!(request.len(5), data.len());
+    assert!(serialize_request(&batch(params), 4).is_err());
+}
+
+#[test]
+fn test_serial_consistency() {
+    let mut params = QueryParams::new(Consistency::Serial, &[]);
+    params.serial_consistency = Some(Consistency::LocalSerial);
+
+    // consistency, serial consistency flag, then the serial consistency
+    for &version in [2u8, 3, 4].iter() {
+        assert_eq!(serialize_params(&params, version).unwrap(), vec![0x00, 0x08, 0x10, 0x00, 0x09], "v{}", version);
+    }
+    assert_eq!(serialize_params(&params, 5).unwrap(), vec![0x00, 0x08, 0x00, 0x00, 0x00, 0x10, 0x00, 0x09]);
+    // v1 has no query flags, only the consistency is sent
+    assert_eq!(serialize_params(&params, 1).unwrap(), vec![0x00, 0x08]);
+}
+
+#[test]
+fn test_query_options() {
+    let mut options = QueryOptions::new(Consistency::Quorum);
+    options.serial_consistency = Some(Consistency::LocalSerial);
+    options.page_size = Some(100);
+    options.paging_state = Some(vec![0x01, 0x02]);
+    options.timestamp = Some(1);
+    options.keyspace = Some("ks".into());
+    // Client side options are not sent
+    options.tracing = true;
+    options.idempotent = true;
+    options.timeout = Some(std::time::Duration::from_secs(1));
+
+    let values = [CqlValue::CqlInt(Some(1))];
+    // consistency, flags (values, page size, paging state, serial consistency, timestamp, keyspace), 1 value,
+    // page size, paging state, serial consistency, timestamp and keyspace
+    assert_eq!(serialize_params(&options.query_params(&values), 5).unwrap(),
+               vec![0x00, 0x04, 0x00, 0x00, 0x00, 0xBD,
+                    0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01,
+                    0x00, 0x00, 0x00, 0x64,
+                    0x00, 0x00, 0x00, 0x02, 0x01, 0x02,
+                    0x00, 0x09,
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+                    0x00, 0x02, 0x6B, 0x73]);
+
+    // Batches have no values nor paging
+    assert_eq!(serialize_batch_params(&options.batch_params(), 5).unwrap(),
+               vec![0x00, 0x04, 0x00, 0x00, 0x00, 0xB0, 0x00, 0x09,
+                    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x6B, 0x73]);
+
+    // A consistency alone converts to options with nothing else set
+    let options: QueryOptions = Consistency::One.into();
+    assert_eq!(serialize_params(&options.query_params(&[]), 4).unwrap(), vec![0x00, 0x01, 0x00]);
+}
@@ -0,0 +1,348 @@
+use std::io::Cursor;
+use cql::*;
+use cql::reader::CqlReader;
+
+// RESULT (v4, stream 1) with tracing id, warnings and custom payload, setting the keyspace "rust"
+static TRACED_RESPONSE: [u8; 54] = [0x84, 0x0E, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00, 0x2D,
+                                    // tracing id
+                                    0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10,
+                                    // warnings: ["warn"]
+                                    0x00, 0x01, 0x00, 0x04, 0x77, 0x61, 0x72, 0x6E,
+                                    // custom payload: {"k": "vv"}
+                                    0x00, 0x01, 0x00, 0x01, 0x6B, 0x00, 0x00, 0x00, 0x02, 0x76, 0x76,
+                                    // body: keyspace set to "rust"
+                                    0x00, 0x00, 0x00, 0x03, 0x00, 0x04, 0x72, 0x75, 0x73, 0x74];
+
+// The same response with only the warnings flag
+static WARNED_RESPONSE: [u8; 27] = [0x84, 0x08, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00, 0x12,
+                                    0x00, 0x01, 0x00, 0x04, 0x77, 0x61, 0x72, 0x6E,
+                                    0x00, 0x00, 0x00, 0x03, 0x00, 0x04, 0x72, 0x75, 0x73, 0x74];
+
+fn read_response(data: &[u8]) -> CqlResponse {
+    let mut reader = Cursor::new(data);
+    let header = reader.read_cql_frame_header(4).unwrap();
+    let response = reader.read_cql_response_body(header, 4, Compression::None, None).unwrap();
+    assert_eq!(reader.position() as usize, data.len());
+    response
+}
+
+fn assert_keyspace(response: &CqlResponse) {
+    match response.body {
+        CqlResponseBody::ResultKeyspace(ref keyspace) => assert_eq!(keyspace, "rust"),
+        ref body => panic!("Unexpected body {:?}", body)
+    }
+}
+
+#[test]
+fn test_response_flags() {
+    let response = read_response(&TRACED_RESPONSE);
+    assert_eq!(response.stream, 1);
+    assert_eq!(response.tracing_id.unwrap().as_bytes().to_vec(), TRACED_RESPONSE[9 .. 25].to_vec());
+    assert_eq!(response.warnings, vec!["warn"]);
+    assert_eq!(response.custom_payload.len(), 1);
+    assert_eq!(response.custom_payload["k"], b"vv".to_vec());
+    assert_keyspace(&response);
+
+    let response = read_response(&WARNED_RESPONSE);
+    assert!(response.tracing_id.is_none());
+    assert_eq!(response.warnings, vec!["warn"]);
+    assert!(response.custom_payload.is_empty());
+    assert_keyspace(&response);
+}
+
+// ROWS result (v4, stream 1) with a single column "ks.t.c" of the unassigned type 0x00FF and one row
+static UNKNOWN_TYPE_ROWS: [u8; 42] = [0x84, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00, 0x21,
+                                      0x00, 0x00, 0x00, 0x02,
+                                      // metadata: global table spec, 1 column
+                                      0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+                                      0x00, 0x02, 0x6B, 0x73, 0x00, 0x01, 0x74,
+                                      0x00, 0x01, 0x63, 0x00, 0xFF,
+                                      // 1 row with a 1 byte value
+                                      0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00];
+
+#[test]
+fn test_unknown_column_type() {
+    let mut reader = Cursor::new(&UNKNOWN_TYPE_ROWS[..]);
+    let header = reader.read_cql_frame_header(4).unwrap();
+    assert!(reader.read_cql_response_body(header, 4, Compression::None, None).is_err());
+}
+
+// ROWS result (v5, stream 1) reporting the new result metadata id "m2", with the varchar column "ks.t.v" and one row
+static CHANGED_METADATA_ROWS: [u8; 50] = [0x85, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00, 0x29,
+                                          0x00, 0x00, 0x00, 0x02,
+                                          // metadata: global table spec and new metadata id, 1 column
+                                          0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x01,
+                                          0x00, 0x02, 0x6D, 0x32,
+                                          0x00, 0x02, 0x6B, 0x73, 0x00, 0x01, 0x74,
+                                          0x00, 0x01, 0x76, 0x00, 0x0D,
+                                          // 1 row: "value"
+                                          0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x05, 0x76, 0x61, 0x6C, 0x75, 0x65];
+
+#[test]
+fn test_update_result_metadata() {
+    let mut reader = Cursor::new(&CHANGED_METADATA_ROWS[..]);
+    let header = reader.read_cql_frame_header(5).unwrap();
+    let rows = match reader.read_cql_response_body(header, 5, Compression::None, None).unwrap().body {
+        CqlResponseBody::ResultRows(rows) => rows,
+        body => panic!("Unexpected body {:?}", body)
+    };
+
+    let mut preps = CqlPreparedStat {
+        id: b"p1".to_vec(),
+        result_metadata_id: Some(b"m1".to_vec()),
+        meta: rows.metadata.clone(),
+        meta_result: None
+    };
+    assert!(preps.update_result_metadata(&rows));
+    assert_eq!(preps.result_metadata_id, Some(b"m2".to_vec()));
+    let meta_result = preps.meta_result.as_ref().unwrap();
+    assert_eq!(meta_result.column_count, 1);
+    assert_eq!(meta_result.row_metadata[0].col_name, "v");
+    assert!(meta_result.new_metadata_id.is_none());
+
+    // Unchanged metadata leaves the statement as it is
+    let mut unchanged = rows;
+    unchanged.metadata.new_metadata_id = None;
+    assert!(!preps.update_result_metadata(&unchanged));
+    assert_eq!(preps.result_metadata_id, Some(b"m2".to_vec()));
+}
+
+// PREPARED result (v4, stream 1) of "SELECT k, v FROM ks.t WHERE k = ?" with id "p1": the bound
+// variable "k" is the partition key (index 0), the result has the varchar column "v"
+static PREPARED_V4: [u8; 68] = [0x84, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00, 0x3B,
+                                0x00, 0x00, 0x00, 0x04,
+                                0x00, 0x02, 0x70, 0x31,
+                                // metadata: global table spec, 2 columns, 1 partition key index
+                                0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+                                0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+                                0x00, 0x02, 0x6B, 0x73, 0x00, 0x01, 0x74,
+                                0x00, 0x01, 0x6B, 0x00, 0x09,
+                                0x00, 0x01, 0x76, 0x00, 0x0D,
+                                // result metadata: global table spec, 1 column
+                                0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+                                0x00, 0x02, 0x6B, 0x73, 0x00, 0x01, 0x74,
+                                0x00, 0x01, 0x76, 0x00, 0x0D];
+
+#[test]
+fn test_prepared_v4() {
+    let preps = match read_response(&PREPARED_V4).body {
+        CqlResponseBody::ResultPrepared(preps) => preps,
+        body => panic!("Unexpected body {:?}", body)
+    };
+    assert_eq!(preps.id, b"p1".to_vec());
+    assert!(preps.result_metadata_id.is_none());
+    assert_eq!(preps.meta.pk_indices, vec![0]);
+    assert_eq!(preps.meta.column_count, 2);
+    assert_eq!(preps.meta.keyspace, "ks");
+    assert_eq!(preps.meta.row_metadata[0].col_name, "k");
+    assert_eq!(preps.meta.row_metadata[1].col_name, "v");
+    let meta_result = preps.meta_result.unwrap();
+    assert!(meta_result.pk_indices.is_empty());
+    assert_eq!(meta_result.row_metadata.len(), 1);
+    assert_eq!(meta_result.row_metadata[0].col_name, "v");
+}
+
+#[test]
+fn test_uuid_value() {
+    let mut data = vec![0x00, 0x00, 0x00, 0x10];
+    data.extend(&TRACED_RESPONSE[9 .. 25]);
+    let mut reader = Cursor::new(&data);
+    let uuid = reader.read_cql_uuid(CqlBytesSize::Cqli32).unwrap().unwrap();
+    assert_eq!(uuid.as_bytes().to_vec(), TRACED_RESPONSE[9 .. 25].to_vec());
+    assert_eq!(reader.position() as usize, data.len());
+
+    let mut reader = Cursor::new(&[0xFF, 0xFF, 0xFF, 0xFF]);
+    assert!(reader.read_cql_uuid(CqlBytesSize::Cqli32).unwrap().is_none());
+    let mut reader = Cursor::new(&[0x00, 0x00, 0x00, 0x04, 0x01, 0x02, 0x03, 0x04]);
+    assert!(reader.read_cql_uuid(CqlBytesSize::Cqli32).is_err());
+}
+
+// ROWS (v4) of a conditional update that was applied: ks.t with the boolean column [applied] only
+static LWT_APPLIED: [u8; 50] = [0x84, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00, 0x29,
+                                0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+                                0x00, 0x02, 0x6B, 0x73, 0x00, 0x01, 0x74,
+                                0x00, 0x09, 0x5B, 0x61, 0x70, 0x70, 0x6C, 0x69, 0x65, 0x64, 0x5D, 0x00, 0x04,
+                                0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01];
+
+// ROWS (v4) of a conditional update that was not applied, with the current value "x" of the varchar column v
+static LWT_REJECTED: [u8; 60] = [0x84, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00, 0x33,
+                                 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+                                 0x00, 0x02, 0x6B, 0x73, 0x00, 0x01, 0x74,
+                                 0x00, 0x09, 0x5B, 0x61, 0x70, 0x70, 0x6C, 0x69, 0x65, 0x64, 0x5D, 0x00, 0x04,
+                                 0x00, 0x01, 0x76, 0x00, 0x0D,
+                                 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x78];
+
+#[test]
+fn test_lwt_result() {
+    let result = read_response(&LWT_APPLIED).lwt_result().unwrap();
+    assert!(result.applied);
+    assert!(result.existing.is_empty());
+
+    let result = read_response(&LWT_REJECTED).lwt_result().unwrap();
+    assert!(!result.applied);
+    assert_eq!(result.existing.len(), 1);
+    match result.existing[0] {
+        (ref name, CqlVarchar(Some(ref value))) => {
+            assert_eq!(name, "v");
+            assert_eq!(value, "x");
+        },
+        ref existing => panic!("Unexpected column {:?}", existing)
+    }
+
+    // Other results are not the outcome of a conditional update
+    assert!(read_response(&WARNED_RESPONSE).lwt_result().is_err());
+}
+
+// Reads the body of an error following its code and message, checking that all of it is consumed
+fn read_error(code: u32, version: u8, data: &[u8]) -> ServerError {
+    let mut reader = Cursor::new(data);
+    let error = reader.read_cql_server_error(code, version).unwrap();
+    assert_eq!(reader.position() as usize, data.len(), "{:?}", error);
+    assert_eq!(error.code(), code);
+    error
+}
+
+// ERROR (v4): Unavailable "x" at QUORUM, 3 replicas required and 1 alive
+static UNAVAILABLE: [u8; 26] = [0x84, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x11,
+                                0x00, 0x00, 0x10, 0x00, 0x00, 0x01, 0x78,
+                                0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01];
+
+#[test]
+fn test_unavailable_error() {
+    match read_response(&UNAVAILABLE).body {
+        CqlResponseBody::ResponseError(ServerError::Unavailable { consistency: Consistency::Quorum, required: 3, alive: 1 }, ref msg) =>
+            assert_eq!(msg, "x"),
+        ref body => panic!("Unexpected body {:?}", body)
+    }
+}
+
+#[test]
+fn test_timeout_errors() {
+    // LOCAL_QUORUM, 1 received out of 2, write type SIMPLE
+    let write_timeout = [0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+                         0x00, 0x06, 0x53, 0x49, 0x4D, 0x50, 0x4C, 0x45];
+    for &version in [4u8, 5].iter() {
+        match read_error(0x1100, version, &write_timeout) {
+            ServerError::WriteTimeout { consistency: Consistency::LocalQuorum, received: 1, block_for: 2, ref write_type, contentions: None } =>
+                assert_eq!(write_type, "SIMPLE"),
+            error => panic!("Unexpected error {:?}", error)
+        }
+    }
+
+    // SERIAL, 0 received out of 2, write type CAS followed by 3 contentions from v5 on
+    let cas_timeout = [0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+                       0x00, 0x03, 0x43, 0x41, 0x53, 0x00, 0x03];
+    match read_error(0x1100, 5, &cas_timeout) {
+        ServerError::WriteTimeout { consistency: Consistency::Serial, received: 0, block_for: 2, ref write_type, contentions: Some(3) } =>
+            assert_eq!(write_type, "CAS"),
+        error => panic!("Unexpected error {:?}", error)
+    }
+    match read_error(0x1100, 4, &cas_timeout[.. 15]) {
+        ServerError::WriteTimeout { contentions: None, .. } => (),
+        error => panic!("Unexpected error {:?}", error)
+    }
+
+    // ONE, 0 received out of 1, data present
+    match read_error(0x1200, 4, &[0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01]) {
+        ServerError::ReadTimeout { consistency: Consistency::One, received: 0, block_for: 1, data_present: true } => (),
+        error => panic!("Unexpected error {:?}", error)
+    }
+
+    // CAS write of unknown outcome: SERIAL, 0 received out of 2
+    match read_error(0x1700, 5, &[0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02]) {
+        ServerError::CasWriteUnknown { consistency: Consistency::Serial, received: 0, block_for: 2 } => (),
+        error => panic!("Unexpected error {:?}", error)
+    }
+}
+
+#[test]
+fn test_failure_errors() {
+    // QUORUM, 1 received out of 2, then 1 failure before v5, or the reason 0x0000 of 127.0.0.1 from v5 on
+    let read_failure_v4 = [0x00, 0x04, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+                           0x00, 0x00, 0x00, 0x01, 0x00];
+    match read_error(0x1300, 4, &read_failure_v4) {
+        ServerError::ReadFailure { consistency: Consistency::Quorum, received: 1, block_for: 2, num_failures: 1, ref reasons, data_present: false } =>
+            assert!(reasons.is_empty()),
+        error => panic!("Unexpected error {:?}", error)
+    }
+    let read_failure_v5 = [0x00, 0x04, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+                           0x00, 0x00, 0x00, 0x01, 0x04, 0x7F, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01];
+    match read_error(0x1300, 5, &read_failure_v5) {
+        ServerError::ReadFailure { num_failures: 1, ref reasons, data_present: true, .. } => {
+            assert_eq!(reasons.len(), 1);
+            assert_eq!(format!("{:?}", reasons[0].0), "Ipv4(127.0.0.1)");
+            assert_eq!(reasons[0].1, 0x0000);
+        },
+        error => panic!("Unexpected error {:?}", error)
+    }
+
+    // ALL, 0 received out of 3, then 2 failures before v5, or the reasons of 127.0.0.1 and ::1 from v5 on,
+    // write type BATCH
+    let write_failure_v4 = [0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03,
+                            0x00, 0x00, 0x00, 0x02, 0x00, 0x05, 0x42, 0x41, 0x54, 0x43, 0x48];
+    match read_error(0x1500, 4, &write_failure_v4) {
+        ServerError::WriteFailure { consistency: Consistency::All, received: 0, block_for: 3, num_failures: 2, ref reasons, ref write_type } => {
+            assert!(reasons.is_empty());
+            assert_eq!(write_type, "BATCH");
+        },
+        error => panic!("Unexpected error {:?}", error)
+    }
+    let write_failure_v5 = [0x00, 0x05, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03,
+                            0x00, 0x00, 0x00, 0x02,
+                            0x04, 0x7F, 0x00, 0x00, 0x01, 0x00, 0x01,
+                            0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02,
+                            0x00, 0x05, 0x42, 0x41, 0x54, 0x43, 0x48];
+    match read_error(0x1500, 5, &write_failure_v5) {
+        ServerError::WriteFailure { num_failures: 2, ref reasons, ref write_type, .. } => {
+            assert_eq!(reasons.len(), 2);
+            assert_eq!(format!("{:?}", reasons[0].0), "Ipv4(127.0.0.1)");
+            assert_eq!(reasons[0].1, 0x0001);
+            assert_eq!(format!("{:?}", reasons[1].0), "Ipv6(::1)");
+            assert_eq!(reasons[1].1, 0x0002);
+            assert_eq!(write_type, "BATCH");
+        },
+        error => panic!("Unexpected error {:?}", error)
+    }
+}
+
+#[test]
+fn test_other_errors() {
+    // Keyspace "ks" and table "t", the table is empty when the keyspace already exists
+    match read_error(0x2400, 4, &[0x00, 0x02, 0x6B, 0x73, 0x00, 0x01, 0x74]) {
+        ServerError::AlreadyExists { ref keyspace, ref table } => {
+            assert_eq!(keyspace, "ks");
+            assert_eq!(table, "t");
+        },
+        error => panic!("Unexpected error {:?}", error)
+    }
+    match read_error(0x2400, 4, &[0x00, 0x02, 0x6B, 0x73, 0x00, 0x00]) {
+        ServerError::AlreadyExists { ref table, .. } => assert_eq!(table, ""),
+        error => panic!("Unexpected error {:?}", error)
+    }
+
+    // [short bytes] id of the statement to prepare again
+    match read_error(0x2500, 4, &[0x00, 0x02, 0x70, 0x31]) {
+        ServerError::Unprepared { ref id } => assert_eq!(id, &vec![0x70, 0x31]),
+        error => panic!("Unexpected error {:?}", error)
+    }
+
+    // Function ks.f(int, text)
+    let function_failure = [0x00, 0x02, 0x6B, 0x73, 0x00, 0x01, 0x66,
+                            0x00, 0x02, 0x00, 0x03, 0x69, 0x6E, 0x74, 0x00, 0x04, 0x74, 0x65, 0x78, 0x74];
+    match read_error(0x1400, 4, &function_failure) {
+        ServerError::FunctionFailure { ref keyspace, ref function, ref arg_types } => {
+            assert_eq!(keyspace, "ks");
+            assert_eq!(function, "f");
+            assert_eq!(arg_types.len(), 2);
+            assert_eq!(arg_types[0], "int");
+            assert_eq!(arg_types[1], "text");
+        },
+        error => panic!("Unexpected error {:?}", error)
+    }
+
+    // Errors without a body
+    match read_error(0x2200, 4, &[]) {
+        ServerError::Invalid => (),
+        error => panic!("Unexpected error {:?}", error)
+    }
+}
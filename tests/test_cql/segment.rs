@@ -0,0 +1,88 @@
+use cql::Compression;
+use cql::segment::*;
+
+// OPTIONS frame (v5, stream 1) wrapped in a self-contained segment
+static OPTIONS_SEGMENT: [u8; 19] = [0x09, 0x00, 0x02, 0xA4, 0xC8, 0xC1,
+                                    0x05, 0x00, 0x00, 0x01, 0x05, 0x00, 0x00, 0x00, 0x00,
+                                    0xB5, 0x55, 0x74, 0x86];
+
+// "abc" in a non self-contained segment
+static ABC_SEGMENT: [u8; 13] = [0x03, 0x00, 0x00, 0x5F, 0x47, 0xC5, 0x61, 0x62, 0x63, 0x08, 0x7A, 0x36, 0xC5];
+
+// "abc" as a literal-only LZ4 block in a self-contained compressed segment
+static ABC_SEGMENT_LZ4: [u8; 16] = [0x04, 0x00, 0x06, 0x00, 0x04, 0x82, 0x40, 0xA1,
+                                    0x30, 0x61, 0x62, 0x63, 0xF8, 0x86, 0x4B, 0x60];
+
+// "abc" sent uncompressed (uncompressed length 0) in a compressed segment
+static ABC_SEGMENT_LZ4_RAW: [u8; 15] = [0x03, 0x00, 0x00, 0x00, 0x04, 0x77, 0x22, 0x83,
+                                        0x61, 0x62, 0x63, 0x08, 0x7A, 0x36, 0xC5];
+
+#[test]
+fn test_segment_encode() {
+    let frame = &OPTIONS_SEGMENT[6..15];
+    let encoded = encode_segments(frame, Compression::None).unwrap();
+    assert_eq!(encoded, OPTIONS_SEGMENT.to_vec());
+
+    let mut buf = vec![];
+    encode_segment(&mut buf, b"abc", false, Compression::None).unwrap();
+    assert_eq!(buf, ABC_SEGMENT.to_vec());
+
+    // "abc" does not shrink when compressed, so it must be sent raw
+    buf.clear();
+    encode_segment(&mut buf, b"abc", true, Compression::Lz4).unwrap();
+    assert_eq!(buf, ABC_SEGMENT_LZ4_RAW.to_vec());
+}
+
+#[test]
+fn test_segment_decode() {
+    let mut decoder = SegmentDecoder::new(Compression::None);
+    decoder.push(&OPTIONS_SEGMENT[..10]);
+    assert!(decoder.next_segment().unwrap().is_none());
+    decoder.push(&OPTIONS_SEGMENT[10..]);
+    let segment = decoder.next_segment().unwrap().unwrap();
+    assert!(segment.self_contained);
+    assert_eq!(segment.payload, OPTIONS_SEGMENT[6..15].to_vec());
+
+    let mut decoder = SegmentDecoder::new(Compression::Lz4);
+    decoder.push(&ABC_SEGMENT_LZ4);
+    decoder.push(&ABC_SEGMENT_LZ4_RAW);
+    assert_eq!(decoder.next_segment().unwrap().unwrap().payload, b"abc".to_vec());
+    assert_eq!(decoder.next_segment().unwrap().unwrap().payload, b"abc".to_vec());
+}
+
+#[test]
+fn test_segment_checksums() {
+    let mut corrupted = ABC_SEGMENT.to_vec();
+    corrupted[1] ^= 0x01;
+    let mut decoder = SegmentDecoder::new(Compression::None);
+    decoder.push(&corrupted);
+    assert!(decoder.next_segment().is_err());
+
+    let mut corrupted = ABC_SEGMENT.to_vec();
+    corrupted[7] ^= 0x01;
+    let mut decoder = SegmentDecoder::new(Compression::None);
+    decoder.push(&corrupted);
+    assert!(decoder.next_segment().is_err());
+}
+
+#[test]
+fn test_segment_split() {
+    // Bigger than a segment, so the frames are split across several segments
+    let payload: Vec<u8> = (0 .. 300000).map(|i| (i % 251) as u8).collect();
+    let encoded = encode_segments(&payload, Compression::Lz4).unwrap();
+
+    let mut decoder = SegmentDecoder::new(Compression::Lz4);
+    let mut decoded = vec![];
+    let mut segments = 0;
+    for chunk in encoded.chunks(4096) {
+        decoder.push(chunk);
+        while let Some(segment) = decoder.next_segment().unwrap() {
+            assert!(!segment.self_contained);
+            decoded.extend(segment.payload);
+            segments += 1;
+        }
+    }
+    assert!(decoder.is_empty());
+    assert_eq!(segments, 3);
+    assert_eq!(decoded, payload);
+}
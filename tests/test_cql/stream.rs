@@ -0,0 +1,170 @@
+use std::time::Duration;
+use cql::*;
+use cql::client::StreamIds;
+use super::fake_server;
+
+#[test]
+fn test_stream_limits() {
+    // v1 and v2 frames carry a signed byte, v3 and later a signed short
+    for &(version, max) in [(2u8, 128i32), (3, 32768), (5, 32768)].iter() {
+        assert_eq!(StreamIds::max_streams(version), max);
+        let mut streams = StreamIds::new(0);
+        for expected in 0 .. max {
+            assert_eq!(streams.alloc(version).unwrap() as i32, expected);
+        }
+        assert!(streams.alloc(version).is_err(), "v{}", version);
+
+        // A released stream is the only one left to allocate
+        streams.release(42);
+        assert!(!streams.is_in_flight(42));
+        assert_eq!(streams.alloc(version).unwrap(), 42);
+        assert!(streams.alloc(version).is_err());
+    }
+}
+
+#[test]
+fn test_stream_reuse() {
+    let mut streams = StreamIds::new(0);
+    assert_eq!(streams.alloc(4).unwrap(), 0);
+    assert_eq!(streams.alloc(4).unwrap(), 1);
+    streams.release(0);
+    // Streams are handed out in turn, skipping the ones still in flight
+    assert_eq!(streams.alloc(4).unwrap(), 2);
+    for _ in 3 .. 32768 {
+        streams.alloc(4).unwrap();
+    }
+    assert_eq!(streams.alloc(4).unwrap(), 0);
+    assert!(streams.alloc(4).is_err());
+}
+
+#[test]
+fn test_abandoned_streams() {
+    let mut streams = StreamIds::new(2);
+    for _ in 0 .. 4 {
+        streams.alloc(4).unwrap();
+    }
+    streams.abandon(0, 4).unwrap();
+    streams.abandon(1, 4).unwrap();
+    assert!(streams.is_abandoned(0));
+    // An abandoned stream stays allocated until its late response arrives
+    assert!(streams.is_in_flight(0));
+    streams.release(0);
+    assert!(!streams.is_abandoned(0));
+    streams.abandon(2, 4).unwrap();
+    // Waiting again for the response reclaims the stream
+    streams.reclaim(2);
+    streams.abandon(3, 4).unwrap();
+    match streams.abandon(2, 4) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::ConnectionError),
+        Ok(_) => panic!("More streams abandoned than allowed")
+    }
+
+    // Never more than half the streams of the protocol version
+    let mut streams = StreamIds::new(1024);
+    for stream in 0 .. 64 {
+        streams.alloc(2).unwrap();
+        streams.abandon(stream, 2).unwrap();
+    }
+    streams.alloc(2).unwrap();
+    assert!(streams.abandon(64, 2).is_err());
+}
+
+fn connect_fake_server(max_abandoned_streams: usize) -> client::Client {
+    let port = fake_server::start();
+    let mut options = ConnectOptions::new();
+    options.request_timeout = Some(Duration::from_millis(200));
+    options.max_abandoned_streams = max_abandoned_streams;
+    try_test!(connect_with_options("127.0.0.1", port, None, &options), "Error connecting to the fake server")
+}
+
+#[test]
+fn test_out_of_order_responses() {
+    let mut client = connect_fake_server(1024);
+    client.request_timeout = Some(fake_server::LATE_RESPONSE_DELAY * 4);
+
+    // The server answers the second query first
+    let late = try_test!(client.send_query("silent", &[], Consistency::One), "Error sending query");
+    let void = try_test!(client.send_query("select v from ks.t", &[], Consistency::One), "Error sending query");
+    assert!(late != void);
+    match client.get_response(late) {
+        Ok(CqlResponse { body: CqlResponseBody::ResultKeyspace(ref keyspace), .. }) => assert_eq!(keyspace, "late"),
+        response => panic!("Unexpected response {:?}", response)
+    }
+    match client.get_response(void) {
+        Ok(CqlResponse { body: CqlResponseBody::ResultVoid, .. }) => (),
+        response => panic!("Unexpected response {:?}", response)
+    }
+    // Each response is claimed once
+    assert!(client.get_response(void).is_err());
+}
+
+#[test]
+fn test_too_many_abandoned_streams() {
+    let mut client = connect_fake_server(1);
+    let first = try_test!(client.send_query("silent", &[], Consistency::One), "Error sending query");
+    let second = try_test!(client.send_query("silent", &[], Consistency::One), "Error sending query");
+    match client.get_response(first) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::TimeoutError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+    assert!(!client.is_defunct());
+    // The second response times out before the first one arrives
+    match client.get_response(second) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::TimeoutError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+    assert!(client.is_defunct());
+    match client.exec_query("select v from ks.t", &[], Consistency::One) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::ConnectionError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_out_of_order_responses() {
+    let port = fake_server::start();
+    let client = try_test!(async_client::connect("127.0.0.1", port, None).await, "Error connecting to the fake server");
+
+    let late = async {
+        let response = client.exec_query("silent", &[], Consistency::One).await;
+        (response, std::time::Instant::now())
+    };
+    let void = async {
+        let response = client.exec_query("select v from ks.t", &[], Consistency::One).await;
+        (response, std::time::Instant::now())
+    };
+    let ((late, late_at), (void, void_at)) = tokio::join!(late, void);
+    match late {
+        Ok(CqlResponse { body: CqlResponseBody::ResultKeyspace(ref keyspace), .. }) => assert_eq!(keyspace, "late"),
+        response => panic!("Unexpected response {:?}", response)
+    }
+    match void {
+        Ok(CqlResponse { body: CqlResponseBody::ResultVoid, .. }) => (),
+        response => panic!("Unexpected response {:?}", response)
+    }
+    assert!(void_at < late_at);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_too_many_abandoned_streams() {
+    let port = fake_server::start();
+    let mut options = ConnectOptions::new();
+    options.request_timeout = Some(Duration::from_millis(200));
+    options.max_abandoned_streams = 1;
+    let client = try_test!(async_client::connect_with_options("127.0.0.1", port, None, &options).await, "Error connecting to the fake server");
+
+    let (first, second) = tokio::join!(client.exec_query("silent", &[], Consistency::One),
+                                       client.exec_query("silent", &[], Consistency::One));
+    for response in [first, second].iter() {
+        match *response {
+            Err(ref err) => assert_eq!(err.kind, RCErrorType::TimeoutError),
+            ref response => panic!("Unexpected response {:?}", response)
+        }
+    }
+    match client.exec_query("select v from ks.t", &[], Consistency::One).await {
+        Err(err) => assert_eq!(err.kind, RCErrorType::ConnectionError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+}
@@ -0,0 +1,168 @@
+use std::net::TcpListener;
+use std::thread;
+use std::time::{Duration, Instant};
+use cql::*;
+use super::fake_server;
+
+#[test]
+fn test_request_timeout() {
+    // Accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let mut options = ConnectOptions::new();
+    options.connect_timeout = Some(Duration::from_secs(1));
+    options.request_timeout = Some(Duration::from_millis(200));
+
+    let start = Instant::now();
+    match connect_with_options("127.0.0.1", port, None, &options) {
+        Ok(_) => panic!("Connected to a server that does not answer"),
+        Err(err) => assert_eq!(err.kind, RCErrorType::TimeoutError)
+    }
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+fn connect_fake_server() -> client::Client {
+    let port = fake_server::start();
+    let mut options = ConnectOptions::new();
+    options.request_timeout = Some(Duration::from_millis(200));
+    let client = try_test!(connect_with_options("127.0.0.1", port, None, &options), "Error connecting to the fake server");
+    assert_eq!(client.version, 4);
+    client
+}
+
+fn assert_void(response: RCResult<CqlResponse>) {
+    match response {
+        Ok(CqlResponse { body: CqlResponseBody::ResultVoid, .. }) => (),
+        response => panic!("Unexpected response {:?}", response)
+    }
+}
+
+#[test]
+fn test_late_response() {
+    let mut client = connect_fake_server();
+
+    let start = Instant::now();
+    match client.exec_query("silent", &[], Consistency::One) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::TimeoutError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+    assert!(start.elapsed() < fake_server::LATE_RESPONSE_DELAY);
+    assert!(!client.is_defunct());
+
+    // The connection is still usable, and the late response is dropped when it arrives
+    assert_void(client.exec_query("select v from ks.t", &[], Consistency::One));
+    thread::sleep(fake_server::LATE_RESPONSE_DELAY);
+    assert_void(client.exec_query("select v from ks.t", &[], Consistency::One));
+}
+
+#[test]
+fn test_idempotent_retry() {
+    let mut client = connect_fake_server();
+
+    // The second attempt of an idempotent query is answered in time
+    let mut options = QueryOptions::new(Consistency::One);
+    options.idempotent = true;
+    let start = Instant::now();
+    assert_void(client.exec_query("once", &[], options));
+    assert!(start.elapsed() < fake_server::LATE_RESPONSE_DELAY);
+
+    // Other queries are not retried
+    let mut client = connect_fake_server();
+    match client.exec_query("once", &[], Consistency::One) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::TimeoutError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+}
+
+#[test]
+fn test_defunct_connection() {
+    let mut client = connect_fake_server();
+    match client.exec_query("close", &[], Consistency::One) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::ConnectionError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+    assert!(client.is_defunct());
+    match client.exec_query("select v from ks.t", &[], Consistency::One) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::ConnectionError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+}
+
+#[test]
+fn test_write_timeout() {
+    let mut client = connect_fake_server();
+    // Much bigger than the socket buffers, and never read by the server
+    let query = "x".repeat(64 << 20);
+    match client.exec_query(&query, &[], Consistency::One) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::TimeoutError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+    // Part of the request may have been written, so the connection cannot be used any more
+    assert!(client.is_defunct());
+    match client.exec_query("select v from ks.t", &[], Consistency::One) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::ConnectionError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_request_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let mut options = ConnectOptions::new();
+    options.connect_timeout = Some(Duration::from_secs(1));
+    options.request_timeout = Some(Duration::from_millis(200));
+
+    let start = Instant::now();
+    match async_client::connect_with_options("127.0.0.1", port, None, &options).await {
+        Ok(_) => panic!("Connected to a server that does not answer"),
+        Err(err) => assert_eq!(err.kind, RCErrorType::TimeoutError)
+    }
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_late_response() {
+    let port = fake_server::start();
+    let mut options = ConnectOptions::new();
+    options.request_timeout = Some(Duration::from_millis(200));
+    let client = try_test!(async_client::connect_with_options("127.0.0.1", port, None, &options).await, "Error connecting to the fake server");
+
+    let start = Instant::now();
+    match client.exec_query("silent", &[], Consistency::One).await {
+        Err(err) => assert_eq!(err.kind, RCErrorType::TimeoutError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+    assert!(start.elapsed() < fake_server::LATE_RESPONSE_DELAY);
+
+    assert_void(client.exec_query("select v from ks.t", &[], Consistency::One).await);
+    tokio::time::sleep(fake_server::LATE_RESPONSE_DELAY).await;
+    assert_void(client.exec_query("select v from ks.t", &[], Consistency::One).await);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_async_idempotent_retry() {
+    let port = fake_server::start();
+    let mut connect_options = ConnectOptions::new();
+    connect_options.request_timeout = Some(Duration::from_millis(200));
+
+    // The second attempt of an idempotent query is answered in time
+    let client = try_test!(async_client::connect_with_options("127.0.0.1", port, None, &connect_options).await, "Error connecting to the fake server");
+    let mut options = QueryOptions::new(Consistency::One);
+    options.idempotent = true;
+    let start = Instant::now();
+    assert_void(client.exec_query("once", &[], options).await);
+    assert!(start.elapsed() < fake_server::LATE_RESPONSE_DELAY);
+
+    // Other queries are not retried
+    let client = try_test!(async_client::connect_with_options("127.0.0.1", port, None, &connect_options).await, "Error connecting to the fake server");
+    match client.exec_query("once", &[], Consistency::One).await {
+        Err(err) => assert_eq!(err.kind, RCErrorType::TimeoutError),
+        response => panic!("Unexpected response {:?}", response)
+    }
+}
@@ -0,0 +1,13 @@
+use cql::timestamp::*;
+
+#[test]
+fn test_monotonic_timestamps() {
+    let generator = MonotonicTimestampGenerator::new();
+    let mut last = generator.next_timestamp();
+    assert!(last > 1_400_000_000_000_000);
+    for _ in 0 .. 1000 {
+        let next = generator.next_timestamp();
+        assert!(next > last);
+        last = next;
+    }
+}
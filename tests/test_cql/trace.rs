@@ -0,0 +1,36 @@
+use cql::*;
+use uuid::Uuid;
+use super::fake_server;
+
+#[test]
+fn test_trace_session() {
+    let port = fake_server::start();
+    let mut client = try_test!(connect("127.0.0.1", port, None), "Error connecting to the fake server");
+
+    // The fake server is still writing the session the first time it is read
+    let session_id = Uuid::parse_str(fake_server::TRACED_SESSION).unwrap();
+    let session = try_test!(client.get_trace_session(&session_id), "Error fetching trace session");
+    assert_eq!(session.session_id, session_id);
+    assert_eq!(format!("{:?}", session.coordinator), "Ipv4(127.0.0.1)");
+    assert_eq!(session.duration, 500);
+    assert_eq!(session.request, "Execute CQL3 query");
+    assert_eq!(session.started_at, 1580000000000);
+    assert_eq!(session.events.len(), 1);
+    let event = &session.events[0];
+    assert_eq!(event.activity, "Parsing query");
+    assert_eq!(format!("{:?}", event.source), "Ipv4(127.0.0.1)");
+    assert_eq!(event.source_elapsed, 42);
+    assert_eq!(event.thread, "Native-Transport-Requests-1");
+}
+
+#[test]
+fn test_trace_session_missing() {
+    let port = fake_server::start();
+    let mut client = try_test!(connect("127.0.0.1", port, None), "Error connecting to the fake server");
+
+    let session_id = Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap();
+    match client.get_trace_session(&session_id) {
+        Err(err) => assert_eq!(err.kind, RCErrorType::NoDataError),
+        Ok(session) => panic!("Unexpected session {:?}", session)
+    }
+}
@@ -0,0 +1,115 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+use cql::*;
+use cql::reader::CqlReader;
+use cql::serialize::CqlSerializable;
+
+// [option] of ks.addr: the UDT id, keyspace, name and the fields street (varchar), zip (int) and tags (list<int>)
+static ADDR_TYPE: [u8; 41] = [0x00, 0x30, 0x00, 0x02, 0x6B, 0x73, 0x00, 0x04, 0x61, 0x64, 0x64, 0x72,
+                              0x00, 0x03,
+                              0x00, 0x06, 0x73, 0x74, 0x72, 0x65, 0x65, 0x74, 0x00, 0x0D,
+                              0x00, 0x03, 0x7A, 0x69, 0x70, 0x00, 0x09,
+                              0x00, 0x04, 0x74, 0x61, 0x67, 0x73, 0x00, 0x20, 0x00, 0x09];
+
+fn addr_type() -> CqlUdtType {
+    match Cursor::new(&ADDR_TYPE[..]).read_cql_type() {
+        Ok(CqlType::Udt(udt)) => udt,
+        col_type => panic!("Unexpected type {:?}", col_type)
+    }
+}
+
+#[test]
+fn test_udt_type() {
+    let mut reader = Cursor::new(&ADDR_TYPE[..]);
+    let udt = match reader.read_cql_type() {
+        Ok(CqlType::Udt(udt)) => udt,
+        col_type => panic!("Unexpected type {:?}", col_type)
+    };
+    assert_eq!(reader.position() as usize, ADDR_TYPE.len());
+    assert_eq!(udt.keyspace, "ks");
+    assert_eq!(udt.name, "addr");
+    let fields: Vec<String> = udt.fields.iter().map(|(name, field_type)| format!("{} {:?}", name, field_type)).collect();
+    assert_eq!(fields, vec!["street Native(ColumnVarChar)", "zip Native(ColumnInt)", "tags List(Native(ColumnInt))"]);
+
+    // A descriptor cut short is an error
+    assert!(Cursor::new(&ADDR_TYPE[.. 30]).read_cql_type().is_err());
+}
+
+// ROWS result (v4, stream 1) with the single column ks.t.a of type ks.addr and one row with the given value
+fn udt_rows(value: &[u8]) -> Vec<u8> {
+    let mut body = vec![0x00, 0x00, 0x00, 0x02,
+                        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+                        0x00, 0x02, 0x6B, 0x73, 0x00, 0x01, 0x74,
+                        0x00, 0x01, 0x61];
+    body.extend(&ADDR_TYPE);
+    body.extend(&[0x00, 0x00, 0x00, 0x01]);
+    body.extend(value);
+    let mut frame = vec![0x84, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00, 0x00, body.len() as u8];
+    frame.extend(body);
+    frame
+}
+
+fn read_udt_row(value: &[u8]) -> CQLUdt {
+    let data = udt_rows(value);
+    let mut reader = Cursor::new(&data);
+    let header = reader.read_cql_frame_header(4).unwrap();
+    let response = reader.read_cql_response_body(header, 4, Compression::None, None).unwrap();
+    assert_eq!(reader.position() as usize, data.len());
+    match response.body {
+        CqlResponseBody::ResultRows(mut rows) => match rows.rows.pop().unwrap().cols.pop() {
+            Some(CqlValue::CqlUdt(Some(fields))) => fields,
+            value => panic!("Unexpected value {:?}", value)
+        },
+        body => panic!("Unexpected body {:?}", body)
+    }
+}
+
+#[test]
+fn test_udt_value() {
+    // The street "x", a null zip and the tags [7]
+    let fields = read_udt_row(&[0x00, 0x00, 0x00, 0x19,
+                                0x00, 0x00, 0x00, 0x01, 0x78,
+                                0xFF, 0xFF, 0xFF, 0xFF,
+                                0x00, 0x00, 0x00, 0x0C, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x07]);
+    assert_eq!(format!("{:?}", fields),
+               r#"[("street", CqlVarchar(Some("x"))), ("zip", CqlInt(None)), ("tags", CqlList(Some([CqlInt(Some(7))])))]"#);
+}
+
+// Fields added to the type after the value was written are left out at the end of it
+#[test]
+fn test_udt_trailing_fields() {
+    let fields = read_udt_row(&[0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x01, 0x78]);
+    assert_eq!(format!("{:?}", fields), r#"[("street", CqlVarchar(Some("x")))]"#);
+
+    let fields = read_udt_row(&[0x00, 0x00, 0x00, 0x00]);
+    assert!(fields.is_empty());
+}
+
+fn serialize(value: &CqlValue) -> Vec<u8> {
+    let mut buf = vec![];
+    try_test!(value.serialize(&mut buf, 4), "Error serializing value");
+    assert_eq!(buf.len(), 4 + value.len(4));
+    buf
+}
+
+// The street "x" and a null zip, the tags are left out
+static ADDR_VALUE: [u8; 13] = [0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x01, 0x78, 0xFF, 0xFF, 0xFF, 0xFF];
+
+#[test]
+fn test_udt_bind() {
+    let value = CqlValue::CqlUdt(Some(vec![(Cow::Borrowed("street"), CqlVarchar(Some(Cow::Borrowed("x")))),
+                                           (Cow::Borrowed("zip"), CqlValue::CqlInt(None))]));
+    assert_eq!(serialize(&value), ADDR_VALUE.to_vec());
+    let mut reader = Cursor::new(&ADDR_VALUE[..]);
+    match reader.read_cql_udt(&addr_type(), CqlBytesSize::Cqli32) {
+        Ok(Some(fields)) => assert_eq!(format!("{:?}", fields), r#"[("street", CqlVarchar(Some("x"))), ("zip", CqlInt(None))]"#),
+        fields => panic!("Unexpected fields {:?}", fields)
+    }
+
+    // Fields are bound by position, their names are not sent
+    let renamed = CqlValue::CqlUdt(Some(vec![(Cow::Borrowed("zip"), CqlVarchar(Some(Cow::Borrowed("x")))),
+                                             (Cow::Borrowed(""), CqlValue::CqlInt(None))]));
+    assert_eq!(serialize(&renamed), ADDR_VALUE.to_vec());
+
+    assert_eq!(serialize(&CqlValue::CqlUdt(None)), vec![0xFF, 0xFF, 0xFF, 0xFF]);
+}